@@ -1,33 +1,54 @@
 use super::perm::*;
+use num_bigint::{BigInt, BigUint};
+use num_rational::BigRational;
+#[cfg(feature = "rand")]
 use rand::Rng;
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
 
-pub struct GroupRNG {
+#[cfg(feature = "rand")]
+pub struct GroupRNG<R: Rng> {
     gens: Vec<Perm>,
     n: usize,
-    id: Perm,
-    rng: rand::rngs::ThreadRng
+    rng: R,
+    // inverses of gens, kept in sync so that step never has to call inv
+    invs: Vec<Perm>,
+    // reused output buffers for products, swapped into place each step
+    scratch: Perm,
+    scratch_inv: Perm
+}
+
+#[cfg(feature = "rand")]
+impl GroupRNG<rand::rngs::ThreadRng> {
+    pub fn new(g: &Group) -> GroupRNG<rand::rngs::ThreadRng> {
+        GroupRNG::with_rng(g, rand::thread_rng())
+    }
 }
 
-impl GroupRNG {
-    pub fn new(g: &Group) -> GroupRNG {
+#[cfg(feature = "rand")]
+impl<R: Rng> GroupRNG<R> {
+    pub fn with_rng(g: &Group, rng: R) -> GroupRNG<R> {
+        // product replacement needs at least two generators to choose from,
+        // so pad with the identity; this is what makes the cyclic group work
+        let mut gens = g.gens.clone();
+        while gens.len() < 2 {
+            gens.push(g.id.clone());
+        }
+        let invs = gens.iter().map(|x| x.inv()).collect();
         GroupRNG {
-            gens: g.gens.clone(),
+            gens,
             n: g.n,
-            id: g.id.clone(),
-            rng: rand::thread_rng()
+            rng,
+            invs,
+            scratch: g.id.clone(),
+            scratch_inv: g.id.clone()
         }
-    } 
+    }
 
     /// applies one step of the product replacement algorithm
     fn step(&mut self) {
-        // special case is needed for cyclic group: product replacement does not apply
-        // here, since there is only 1 generator. In this case, we apply a 
-        // random power to the basis point
-        
-        
-
         // following notation of https://www.math.ucla.edu/~pak/papers/what9.pdf
         let i = self.rng.gen_range(0..(self.gens.len()));
         let j = {
@@ -36,22 +57,33 @@ impl GroupRNG {
         };
         let pi = &self.gens[i];
         let pj = &self.gens[j];
+        let ii = &self.invs[i];
+        let ij = &self.invs[j];
         
-        let chooseR : bool = self.rng.gen_bool(0.5);
+        let choose_r : bool = self.rng.gen_bool(0.5);
         let chooseplus : bool = self.rng.gen_bool(0.5);
-        if chooseR {
+        let out = &mut self.scratch;
+        let out_inv = &mut self.scratch_inv;
+        // the new inverse is the product of the cached inverses in reverse
+        if choose_r {
             if chooseplus {
-                self.gens[i] = pi.compose(&pj);
+                pi.compose_into(pj, out);
+                ij.compose_into(ii, out_inv);
             } else {
-                self.gens[i] = pi.compose(&pj.inv())
+                pi.compose_into(ij, out);
+                pj.compose_into(ii, out_inv);
             }
         } else {
             if chooseplus {
-                self.gens[i] = pj.compose(&pi);
+                pj.compose_into(pi, out);
+                ii.compose_into(ij, out_inv);
             } else {
-                self.gens[i] = pj.inv().compose(&pi);
+                ij.compose_into(pi, out);
+                ii.compose_into(pj, out_inv);
             }
         }
+        std::mem::swap(&mut self.gens[i], &mut self.scratch);
+        std::mem::swap(&mut self.invs[i], &mut self.scratch_inv);
     }
 
     fn draw(&mut self) -> Perm {
@@ -91,11 +123,10 @@ impl<'a> StabView<'a> {
         let mut frontier = Vec::new();
         s[point] = Some(g.id());
         frontier.push(point);
-        while !frontier.is_empty() {
-            let top = frontier.pop().unwrap();
+        while let Some(top) = frontier.pop() {
             for g in gens.iter() {
                 let newp = g.apply(top);
-                if !s[newp].is_none() { continue }
+                if s[newp].is_some() { continue }
                 s[newp] = Some(g);
                 frontier.push(newp);
             }
@@ -104,7 +135,8 @@ impl<'a> StabView<'a> {
         StabView { g: gens, base, schreier: s, point, id: g.id()}
     }
 
-    pub fn add_gen(&mut self, g: &'a Perm) -> () {
+    #[allow(dead_code)]
+    pub fn add_gen(&mut self, g: &'a Perm) {
         // first filter it: see if it fixes the base
         if !self.base.iter().all(|p| g.apply(*p) == *p) { return }
 
@@ -118,11 +150,10 @@ impl<'a> StabView<'a> {
         let point = self.point;
         self.schreier[point] = Some(self.id);
         frontier.push(point);
-        while !frontier.is_empty() {
-            let top = frontier.pop().unwrap();
+        while let Some(top) = frontier.pop() {
             for g in self.g.iter() {
                 let newp = g.apply(top);
-                if !self.schreier[newp].is_none() { continue }
+                if self.schreier[newp].is_some() { continue }
                 self.schreier[newp] = Some(g);
                 frontier.push(newp);
             }
@@ -132,37 +163,642 @@ impl<'a> StabView<'a> {
     /// Find the representative of g
     pub fn repr(&self, g: &Perm) -> Perm {
         // apply g to the point and return that entry
-        let mut curp = g.apply(self.point);
+        self.coset_rep(g.apply(self.point))
+    }
+
+    /// Find the representative that maps the basis point to p, or the
+    /// identity if p is not in the basis orbit
+    pub fn coset_rep(&self, p: usize) -> Perm {
+        let mut curp = p;
         if self.schreier[curp].is_none() {
             return self.id.clone();
         }
 
         let mut r = self.id.clone();
-        // work backwards until we are back at the original point
+        // work backwards until we are back at the original point; the edges
+        // are visited in reverse, so each one is applied before the ones
+        // already accumulated in r
         while curp != self.point {
             let curg = self.schreier[curp].unwrap();
             curp = curg.inv().apply(curp);
             // println!("curp: {:?}, curg: {:?}", curp, curg);
-            r = curg.compose(&r);
+            r = r.compose(curg);
         }
-        return r;
+        r
     }
 
 
     pub fn base_orbit_size(&self) -> usize {
         // return # non-none entries in the schreier vector
-        return self.schreier.iter().map(|x| if x.is_none() { 0 } else { 1 }).sum()
+        self.schreier.iter().map(|x| if x.is_none() { 0 } else { 1 }).sum()
+    }
+
+    /// The length of the longest path from the base point in the Schreier
+    /// tree, which is the most generators a coset representative needs
+    pub fn tree_depth(&self) -> usize {
+        let mut depth : Vec<Option<usize>> = vec![None; self.schreier.len()];
+        depth[self.point] = Some(0);
+        for p in 0..self.schreier.len() {
+            if self.schreier[p].is_none() { continue }
+            // walk back to a point of known depth, then fill in the path
+            let mut path = Vec::new();
+            let mut curp = p;
+            while depth[curp].is_none() {
+                path.push(curp);
+                curp = self.schreier[curp].unwrap().inv().apply(curp);
+            }
+            let d = depth[curp].unwrap();
+            for (i, &q) in path.iter().rev().enumerate() {
+                depth[q] = Some(d + i + 1);
+            }
+        }
+        depth.into_iter().flatten().max().unwrap_or(0)
+    }
+}
+
+// sift g through the levels of a stabilizer chain, returning the residue and
+// the index of the level at which it stopped (levels.len() if it got through
+// every level)
+fn sift_levels(levels: &[StabView], g: &Perm) -> (Perm, usize) {
+    let mut curg = g.clone();
+    // printing out the trace of this is quite educational!
+    for (i, s) in levels.iter().enumerate() {
+        let curp = curg.apply(s.point);
+        if s.schreier[curp].is_none() {
+            return (curg, i);
+        }
+        curg = s.repr(&curg).inv().compose(&curg);
+    }
+    (curg, levels.len())
+}
+
+/// A stabilizer chain for a group with a strong generating set, produced by
+/// `Group::stabilizer_chain`. Level i of the chain is the stabilizer of the
+/// first i base points, together with the orbit of base point i under it
+/// and a transversal of coset representatives for that orbit
+///
+/// ```
+/// use rs_perm::group::Group;
+/// use rs_perm::perm::Perm;
+///
+/// let mut g = Group::symmetric(5);
+/// let chain = g.stabilizer_chain();
+/// let (residue, level) = chain.sift(&Perm::new_cyc(5, vec![vec![0, 3, 1], vec![2, 4]]));
+/// assert_eq!(residue, Perm::id(5));
+/// assert_eq!(level, chain.len());
+/// ```
+#[derive(Debug, Clone)]
+pub struct StabilizerChain<'a> {
+    base: Vec<usize>,
+    levels: Vec<StabView<'a>>,
+}
+
+impl<'a> StabilizerChain<'a> {
+    /// The base points, in the order they are stabilized
+    pub fn base(&self) -> &[usize] {
+        &self.base
+    }
+
+    /// The number of levels, which is the length of the base
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// The sorted orbit of the base point at the given level under the
+    /// stabilizer of the base points before it
+    pub fn orbit(&self, level: usize) -> Vec<usize> {
+        let s = &self.levels[level];
+        (0..s.schreier.len()).filter(|&p| s.schreier[p].is_some()).collect()
+    }
+
+    /// The coset representative at the given level that maps the base point
+    /// to p, or None if p is not in the orbit
+    pub fn coset_rep(&self, level: usize, p: usize) -> Option<Perm> {
+        let s = &self.levels[level];
+        s.schreier[p].map(|_| s.coset_rep(p))
+    }
+
+    /// The coset representatives at the given level, in the order of `orbit`
+    pub fn transversal(&self, level: usize) -> Vec<Perm> {
+        self.orbit(level).into_iter().map(|p| self.levels[level].coset_rep(p)).collect()
+    }
+
+    /// The depth of the Schreier tree at each level; deep trees mean long
+    /// coset representatives, which suggests a poor choice of generators
+    pub fn schreier_tree_depths(&self) -> Vec<usize> {
+        self.levels.iter().map(|s| s.tree_depth()).collect()
+    }
+
+    /// Sift p through the chain, returning the residue and the level at
+    /// which sifting stopped; p is in the group iff the residue is the
+    /// identity, and then the level is `self.len()`
+    pub fn sift(&self, p: &Perm) -> (Perm, usize) {
+        sift_levels(&self.levels, p)
+    }
+}
+
+/// A condition met by every element of the subgroup sought by
+/// `Group::subgroup_search_refined`, used to refine the ordered partitions
+/// of the search
+pub enum Refiner<'a> {
+    /// every element x keeps the label of each point: labels[x(p)] ==
+    /// labels[p], e.g. the membership of a set it stabilizes
+    Labels(Vec<usize>),
+    /// every element commutes with the permutation
+    Centralizes(&'a Perm),
+}
+
+// an ordered partition of the points: a sequence of cells, along with the
+// index of the cell holding each point
+#[derive(Clone)]
+struct Partition {
+    cells: Vec<Vec<usize>>,
+    cell_of: Vec<usize>,
+}
+
+impl Partition {
+    fn new(n: usize) -> Partition {
+        let cells = if n == 0 { Vec::new() } else { vec![(0..n).collect()] };
+        Partition { cells, cell_of: vec![0; n] }
+    }
+
+    // replace each cell by the points in it with each value of `key`, in
+    // increasing order of the value; returns whether any cell was split
+    fn split_by<K: Ord>(&mut self, key: impl Fn(usize) -> K) -> bool {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for cell in self.cells.iter() {
+            let mut cell = cell.clone();
+            cell.sort_by_key(|&p| key(p));
+            cells.extend(cell.chunk_by(|&a, &b| key(a) == key(b)).map(|c| c.to_vec()));
+        }
+        if cells.len() == self.cells.len() {
+            return false;
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            for &p in cell.iter() {
+                self.cell_of[p] = i;
+            }
+        }
+        self.cells = cells;
+        true
+    }
+
+    // split p off from its cell, ahead of the rest of it
+    fn individualize(&mut self, p: usize) {
+        let c = self.cell_of[p];
+        if self.cells[c].len() == 1 { return }
+        self.cells[c].retain(|&q| q != p);
+        self.cells.insert(c, vec![p]);
+        for cell in self.cells[(c + 1)..].iter() {
+            for &q in cell.iter() {
+                self.cell_of[q] += 1;
+            }
+        }
+    }
+
+    // split the cells by every refiner until none of them splits a cell.
+    // A point keeps its label under the elements sought, and their images
+    // correspond cell by cell, so the same splits happen on both sides.
+    // Every cell has a single label once the labels have been applied, so
+    // after the first refinement only the commuting refiners can split
+    fn refine(&mut self, refiners: &[Refiner], labels: bool) {
+        if labels {
+            for r in refiners.iter() {
+                if let Refiner::Labels(l) = r {
+                    self.split_by(|p| l[p]);
+                }
+            }
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for r in refiners.iter() {
+                // x(g(p)) = g(x(p)), so p and x(p) have g-images in
+                // corresponding cells
+                if let Refiner::Centralizes(g) = r {
+                    let cell_of = self.cell_of.clone();
+                    changed |= self.split_by(|p| cell_of[g.apply(p)]);
+                }
+            }
+        }
+    }
+
+    fn shape(&self) -> Vec<usize> {
+        self.cells.iter().map(|c| c.len()).collect()
+    }
+}
+
+// the length of the cycle of g through each point
+fn cycle_lengths(g: &Perm) -> Vec<usize> {
+    let mut lengths = vec![1; g.len()];
+    for c in g.cycles() {
+        for &p in c.iter() {
+            lengths[p] = c.len();
+        }
+    }
+    lengths
+}
+
+// insert g into the generators kept by Jerrum's filter. Each generator is
+// an edge in a graph on the points, joining its least moved point i to
+// g(i), and the filter keeps this graph a forest. When the new edge closes
+// a cycle, the product of the labels around the cycle, starting from the
+// cycle's least point m, fixes m and every point below it; one edge at m is
+// dropped in favour of the new edge, and the product is inserted in turn
+fn jerrum_insert(edges: &mut Vec<(usize, usize, Perm)>, mut g: Perm) {
+    while let Some(&i) = g.support().first() {
+        let j = g.apply(i);
+        let path = match forest_path(edges, i, j) {
+            None => {
+                edges.push((i, j, g));
+                return;
+            }
+            Some(path) => path,
+        };
+        // the cycle as a list of steps (from, to, edge); None is the new edge
+        let mut steps : Vec<(usize, usize, Option<usize>)> = path;
+        steps.push((j, i, None));
+        let start = (0..steps.len()).min_by_key(|&k| steps[k].0).unwrap();
+        steps.rotate_left(start);
+        let mut h = Perm::id(g.len());
+        for &(from, to, e) in steps.iter() {
+            let label = match e { Some(e) => &edges[e].2, None => &g };
+            let step = if label.apply(from) == to { label.clone() } else { label.inv() };
+            h = step.compose(&h);
+        }
+        if let Some(e) = steps[0].2 {
+            edges.remove(e);
+            edges.push((i, j, g));
+        }
+        g = h;
+    }
+}
+
+// the path from a to b in the forest, as a list of (from, to, edge index)
+fn forest_path(edges: &[(usize, usize, Perm)], a: usize, b: usize) -> Option<Vec<(usize, usize, Option<usize>)>> {
+    // depth first search recording the edge used to reach each point
+    let mut prev : HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut frontier = vec![a];
+    prev.insert(a, (a, usize::MAX));
+    while let Some(top) = frontier.pop() {
+        if top == b { break }
+        for (k, &(x, y, _)) in edges.iter().enumerate() {
+            let other = if x == top { y } else if y == top { x } else { continue };
+            if prev.contains_key(&other) { continue }
+            prev.insert(other, (top, k));
+            frontier.push(other);
+        }
+    }
+    if !prev.contains_key(&b) { return None }
+    let mut path = Vec::new();
+    let mut cur = b;
+    while cur != a {
+        let (p, k) = prev[&cur];
+        path.push((p, cur, Some(k)));
+        cur = p;
+    }
+    path.reverse();
+    Some(path)
+}
+
+// a word in the generators of a group: each letter is the index of a
+// generator and whether it is inverted, and the word [a, b] stands for the
+// product a * b
+pub(crate) type Word = Vec<(usize, bool)>;
+
+// the product of two words, cancelling adjacent inverse letters
+pub(crate) fn word_mul(a: &Word, b: &Word) -> Word {
+    let mut r = a.clone();
+    for &(g, inv) in b.iter() {
+        if r.last() == Some(&(g, !inv)) {
+            r.pop();
+        } else {
+            r.push((g, inv));
+        }
+    }
+    r
+}
+
+pub(crate) fn word_inv(a: &Word) -> Word {
+    a.iter().rev().map(|&(g, inv)| (g, !inv)).collect()
+}
+
+// a stabilizer chain in which every strong generator and coset
+// representative also carries a word in the original generators; it is
+// built by the same deterministic Schreier-Sims as `Group::schreier_sims`
+#[derive(Debug)]
+struct WordChain {
+    base: Vec<usize>,
+    strong: Vec<(Perm, Word)>,
+    /// for each level, the coset representative mapping the base point to
+    /// each point of its orbit
+    levels: Vec<Vec<Option<(Perm, Word)>>>,
+}
+
+impl WordChain {
+    fn new(gens: &[Perm], base: &[usize]) -> WordChain {
+        let n = base.len();
+        let mut c = WordChain {
+            base: base.to_vec(),
+            strong: gens.iter().enumerate().map(|(i, g)| (g.clone(), vec![(i, false)])).collect(),
+            levels: Vec::new(),
+        };
+        c.build_levels();
+        let mut level = n;
+        while level > 0 {
+            match c.schreier_residue(level - 1) {
+                Some((r, w)) => {
+                    let stop = c.base.iter().position(|&b| r.apply(b) != b).unwrap();
+                    c.strong.push((r, w));
+                    c.build_levels();
+                    level = stop + 1;
+                }
+                None => level -= 1,
+            }
+        }
+        c
+    }
+
+    // the strong generators fixing the first `level` base points
+    fn level_gens(&self, level: usize) -> Vec<&(Perm, Word)> {
+        self.strong.iter().filter(|(g, _)| self.base[..level].iter().all(|&b| g.apply(b) == b)).collect()
+    }
+
+    // recompute the transversal at every level by a search over the orbit
+    fn build_levels(&mut self) {
+        let n = self.base.len();
+        let mut levels = Vec::new();
+        for (level, &b) in self.base.iter().enumerate() {
+            let gens = self.level_gens(level);
+            let mut reps : Vec<Option<(Perm, Word)>> = vec![None; n];
+            reps[b] = Some((Perm::id(n), Vec::new()));
+            let mut frontier = vec![b];
+            while let Some(top) = frontier.pop() {
+                for (g, w) in gens.iter() {
+                    let newp = g.apply(top);
+                    if reps[newp].is_some() { continue }
+                    let (u, uw) = reps[top].as_ref().unwrap();
+                    reps[newp] = Some((g.compose(u), word_mul(w, uw)));
+                    frontier.push(newp);
+                }
+            }
+            levels.push(reps);
+        }
+        self.levels = levels;
+    }
+
+    // sift (p, w) through the levels from `level` down, returning the residue
+    // along with its word
+    fn sift(&self, level: usize, p: &Perm, w: &Word) -> (Perm, Word) {
+        let mut h = p.clone();
+        let mut hw = w.clone();
+        for (reps, &b) in self.levels[level..].iter().zip(self.base[level..].iter()) {
+            match &reps[h.apply(b)] {
+                None => break,
+                Some((u, uw)) => {
+                    h = u.inv().compose(&h);
+                    hw = word_mul(&word_inv(uw), &hw);
+                }
+            }
+        }
+        (h, hw)
+    }
+
+    fn schreier_residue(&self, level: usize) -> Option<(Perm, Word)> {
+        let n = self.base.len();
+        for (up, upw) in self.levels[level].iter().flatten() {
+            for (g, gw) in self.level_gens(level) {
+                let gp = g.apply(up.apply(self.base[level]));
+                let (ugp, ugpw) = self.levels[level][gp].as_ref().unwrap();
+                let sg = ugp.inv().compose(g).compose(up);
+                let sgw = word_mul(&word_mul(&word_inv(ugpw), gw), upw);
+                let (r, rw) = self.sift(level + 1, &sg, &sgw);
+                if r != Perm::id(n) {
+                    return Some((r, rw));
+                }
+            }
+        }
+        None
+    }
+}
+
+// the elements of k fixing each of `images`, or None if only the identity
+// does. Each is found from the one for all but the last image, by changing
+// the base of that group to start with the last image, and kept in `cache`
+fn fixing_images(k: &Group, cache: &mut HashMap<Vec<usize>, Option<Rc<Group>>>, images: &[usize]) -> Option<Rc<Group>> {
+    if let Some(s) = cache.get(images) {
+        return s.clone();
+    }
+    let s = match images.split_last() {
+        None => Some(Rc::new(k.clone())),
+        Some((&last, rest)) => fixing_images(k, cache, rest).map(|parent| {
+            let mut h = (*parent).clone();
+            h.change_base(&[last]);
+            h.gens.retain(|x| x.apply(last) == last);
+            Rc::new(h)
+        }),
+    }.filter(|h| h.gens.iter().any(|x| !x.is_identity()));
+    cache.insert(images.to_vec(), s.clone());
+    s
+}
+
+/// Iterator over every element of a group, produced by `Group::elements`
+///
+/// Each element is written uniquely as a product u_0 u_1 ... u_k of coset
+/// representatives, one per level of the stabilizer chain, so iterating over
+/// all choices of representatives visits each element exactly once
+pub struct GroupElementIter {
+    /// coset representatives for each nontrivial level of the chain
+    transversals: Vec<Vec<Perm>>,
+    /// index of the current representative at each level
+    idx: Vec<usize>,
+    done: bool,
+    id: Perm,
+}
+
+impl Iterator for GroupElementIter {
+    type Item = Perm;
+
+    fn next(&mut self) -> Option<Perm> {
+        if self.done { return None }
+        let mut r = self.id.clone();
+        for (t, &i) in self.transversals.iter().zip(self.idx.iter()) {
+            r = r.compose(&t[i]);
+        }
+        // advance the indices like an odometer, starting from the last level
+        self.done = true;
+        for l in (0..self.idx.len()).rev() {
+            self.idx[l] += 1;
+            if self.idx[l] < self.transversals[l].len() {
+                self.done = false;
+                break;
+            }
+            self.idx[l] = 0;
+        }
+        Some(r)
+    }
+}
+
+/// The cycle index of a permutation group, a polynomial in variables a_1,
+/// a_2, ... where a_i stands for a cycle of length i. Each monomial is keyed
+/// by its exponents, so that a_1^2 a_3 is `[2, 0, 1]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleIndex {
+    terms: BTreeMap<Vec<usize>, BigRational>,
+}
+
+impl CycleIndex {
+    /// The monomials with nonzero coefficients
+    pub fn terms(&self) -> &BTreeMap<Vec<usize>, BigRational> {
+        &self.terms
+    }
+
+    /// The coefficient of the monomial with the given exponents; trailing
+    /// zero exponents may be left off
+    pub fn coefficient(&self, exponents: &[usize]) -> BigRational {
+        let len = exponents.iter().rposition(|&e| e != 0).map_or(0, |i| i + 1);
+        self.terms.get(&exponents[..len]).cloned()
+            .unwrap_or_else(|| BigRational::from_integer(BigInt::from(0)))
+    }
+
+    /// Evaluate the polynomial with a_i set to vars[i - 1]; substituting c
+    /// for every variable counts the colorings with c colors up to symmetry
+    pub fn substitute(&self, vars: &[BigUint]) -> BigRational {
+        self.terms.iter().map(|(exps, coeff)| {
+            assert!(exps.len() <= vars.len(), "no value for a_{}", exps.len());
+            let value : BigUint = exps.iter().zip(vars.iter())
+                .map(|(&e, v)| v.pow(e as u32))
+                .product();
+            coeff * BigRational::from_integer(BigInt::from(value))
+        }).sum()
+    }
+}
+
+/// The largest order for which `Group::cayley_table` builds a table
+pub const CAYLEY_TABLE_MAX_ORDER: usize = 5040;
+
+/// Statistics about a run of `Group::random_schreier_sims`
+#[derive(Debug, Clone, Copy)]
+pub struct SchreierSimsStats {
+    /// number of new strong generators added to the group
+    pub generators_added: usize,
+    /// number of random elements that were sifted
+    pub iterations: usize,
+}
+
+/// Errors arising from parsing a group written in GAP notation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// the text is not of the form `Group(...)`
+    Syntax(String),
+    /// the generator at position `index` is not a valid permutation
+    Generator { index: usize, error: PermError },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Syntax(msg) => write!(f, "syntax error: {}", msg),
+            ParseError::Generator { index, error } =>
+                write!(f, "generator {}: {}", index, error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Errors arising from building a group out of invalid generators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupError {
+    /// no generators were given, so the degree is unknown
+    NoGenerators,
+    /// the generator at position `index` has degree `found` rather than the
+    /// degree `expected` of the first generator
+    DegreeMismatch { index: usize, expected: usize, found: usize },
+}
+
+impl std::fmt::Display for GroupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GroupError::NoGenerators => write!(f, "no generators given"),
+            GroupError::DegreeMismatch { index, expected, found } =>
+                write!(f, "generator {} has degree {} but expected {}", index, found, expected),
+        }
     }
 }
 
+impl std::error::Error for GroupError {}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GroupRepr", into = "GroupRepr"))]
 pub struct Group {
     /// vector of generators
     gens: Vec<Perm>, 
+    /// the generators the group was constructed with; `gens` starts as a
+    /// copy of these and grows into a strong generating set
+    original_gens: Vec<Perm>,
     /// number of points
     n: usize,
     /// identity element
     id: Perm,
+    /// ordering of all points used as the base of the stabilizer chain
+    base: Vec<usize>,
+    /// whether `gens` is known to be a strong generating set for `base`
+    is_sgs: bool,
+    /// the chain with words in `original_gens` used by `factor`, built by
+    /// its first call and dropped when the generators or base change
+    words: Option<Arc<WordChain>>,
+}
+
+/// The serialized form of a group: its degree and generators, and the base
+/// when the generators are a strong generating set for it. A reloaded group
+/// with a base is checked with `verify_bsgs`, so it does not need to run
+/// Schreier-Sims again
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GroupRepr {
+    degree: usize,
+    gens: Vec<Perm>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<Vec<usize>>,
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<GroupRepr> for Group {
+    type Error = String;
+    fn try_from(r: GroupRepr) -> Result<Group, String> {
+        if let Some(g) = r.gens.iter().find(|g| g.len() != r.degree) {
+            return Err(format!("generator {} does not have degree {}", g, r.degree));
+        }
+        let mut g = if r.gens.is_empty() { Group::trivial(r.degree) } else { Group::new(r.gens) };
+        if let Some(base) = r.base {
+            let mut in_base = vec![false; r.degree];
+            for &b in base.iter() {
+                if b >= r.degree || in_base[b] {
+                    return Err(format!("base point {} is out of range or repeated", b));
+                }
+                in_base[b] = true;
+            }
+            let mut full_base = base;
+            full_base.extend((0..r.degree).filter(|&p| !in_base[p]));
+            g.is_sgs = g.verify_bsgs(&full_base);
+            g.base = full_base;
+        }
+        Ok(g)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Group> for GroupRepr {
+    fn from(g: Group) -> GroupRepr {
+        let base = if g.is_sgs { Some(g.base) } else { None };
+        GroupRepr { degree: g.n, gens: g.gens, base }
+    }
 }
 
 impl Group {
@@ -171,11 +807,17 @@ impl Group {
     }
 
     pub fn num_gens(&self) -> usize {
-        self.n
+        self.gens.len()
     }
 
     pub fn num_points(&self) -> usize {
-        return self.n;
+        self.n
+    }
+
+    /// The generators the group was constructed with, which `factor` writes
+    /// its words in; `get_gens()` may also hold strong generators added since
+    pub fn original_gens(&self) -> &Vec<Perm> {
+        &self.original_gens
     }
 
     pub fn get_gens(&self) -> &Vec<Perm> {
@@ -184,35 +826,178 @@ impl Group {
 
     // strip the point according to the stabilizer chain
     // returns the residue
-    fn strip(&self, stabchain: &Vec<StabView>, g: &Perm) -> Perm {
-        let mut curg = g.clone();
-        // printing out the trace of this is quite educational!
-        for s in stabchain.iter() {
-            let curp = curg.apply(s.point);
-            if s.schreier[curp].is_none() {
-                return curg;
-            }
-            curg = s.repr(&curg).inv().compose(&curg);
-        }
-        return curg;
+    fn strip(&self, stabchain: &[StabView], g: &Perm) -> Perm {
+        sift_levels(stabchain, g).0
     }
 
-    fn gen_stab_chain(&self, base: &Vec<usize>) -> Vec<StabView> {
+    fn gen_stab_chain(&self, base: &[usize]) -> Vec<StabView<'_>> {
         let mut stab_chain = Vec::new();
         let mut sub_base = Vec::new();
-        for i in 0..(base.len()) {
-            stab_chain.push(StabView::new(self, sub_base.clone(), i));
-            sub_base.push(i);
+        for &b in base.iter() {
+            stab_chain.push(StabView::new(self, sub_base.clone(), b));
+            sub_base.push(b);
+        }
+        stab_chain
+    }
+
+    // find a schreier generator at the given level of the chain that does not
+    // strip through the levels below it, and return its residue
+    fn schreier_residue(&self, stabchain: &[StabView], level: usize) -> Option<Perm> {
+        let s = &stabchain[level];
+        for p in 0..self.n {
+            if s.schreier[p].is_none() { continue }
+            let up = s.coset_rep(p);
+            for g in s.g.iter() {
+                // the schreier generator u_{g(p)}^-1 g u_p fixes the basis point
+                let ugp = s.coset_rep(g.apply(p));
+                let sg = ugp.inv().compose(g).compose(&up);
+                let residue = self.strip(&stabchain[(level+1)..], &sg);
+                if !residue.is_identity() {
+                    return Some(residue);
+                }
+            }
+        }
+        None
+    }
+
+    /// Expand the generating set of this group to a strong generating set
+    /// using the deterministic Schreier-Sims algorithm
+    pub fn schreier_sims(&mut self) {
+        let base = self.base.clone();
+        // invariant: every level of the chain at or below `level` is
+        // complete, i.e. its schreier generators all strip to the identity
+        let mut level = base.len();
+        while level > 0 {
+            let residue = {
+                let stab_chain = self.gen_stab_chain(&base);
+                self.schreier_residue(&stab_chain, level - 1)
+            };
+            match residue {
+                Some(r) => {
+                    // the residue fixes every base point up to the level at
+                    // which it stopped stripping; adding it as a generator
+                    // extends every level down to that one, so resume there
+                    let stop = base.iter().position(|&b| r.apply(b) != b).unwrap();
+                    self.gens.push(r);
+                    level = stop + 1;
+                }
+                None => level -= 1,
+            }
+        }
+        self.is_sgs = true;
+    }
+
+    /// Test whether `base` is a base and the generators are a strong
+    /// generating set relative to it, by checking that at every level of the
+    /// chain each schreier generator strips to the identity through the
+    /// levels below it
+    pub fn verify_bsgs(&self, base: &[usize]) -> bool {
+        let mut in_base = vec![false; self.n];
+        for &b in base.iter() {
+            if b >= self.n || in_base[b] { return false }
+            in_base[b] = true;
+        }
+        if base.is_empty() {
+            return self.gens.iter().all(|g| g.is_identity());
+        }
+        let chain = self.gen_stab_chain(base);
+        (0..chain.len()).all(|level| self.schreier_residue(&chain, level).is_none())
+    }
+
+    /// Run the deterministic Schreier-Sims algorithm with a base that begins
+    /// with the given points; the remaining points are appended in order
+    pub fn schreier_sims_with_base(&mut self, base: &[usize]) {
+        let mut in_base = vec![false; self.n];
+        for &b in base.iter() {
+            assert!(b < self.n, "base point {} is out of range", b);
+            assert!(!in_base[b], "base point {} is repeated", b);
+            in_base[b] = true;
+        }
+        let mut full_base = base.to_vec();
+        full_base.extend((0..self.n).filter(|&p| !in_base[p]));
+        self.base = full_base;
+        self.schreier_sims();
+    }
+
+    /// Change the base to one beginning with the given points, keeping the
+    /// strong generating set where possible instead of recomputing it. When
+    /// the new point at a level is in the basic orbit there, conjugating the
+    /// strong generators by the coset representative moving the old base
+    /// point to it gives a strong generating set for the conjugated base.
+    /// Otherwise the point is moved up the base, and once all points are
+    /// placed the generating set is completed by sifting random elements
+    /// until the chain has the order of the group, which is known from the
+    /// old chain (or by the deterministic algorithm without the `rand`
+    /// feature)
+    pub fn change_base(&mut self, new_base: &[usize]) {
+        let mut in_base = vec![false; self.n];
+        for &b in new_base.iter() {
+            assert!(b < self.n, "base point {} is out of range", b);
+            assert!(!in_base[b], "base point {} is repeated", b);
+            in_base[b] = true;
+        }
+        if !self.is_sgs {
+            self.schreier_sims_with_base(new_base);
+            return;
+        }
+        let order = self.order_big();
+        for (i, &t) in new_base.iter().enumerate() {
+            if self.base[i] == t { continue }
+            let u = {
+                let chain = self.gen_stab_chain(&self.base[..(i + 1)]);
+                chain[i].schreier[t].map(|_| chain[i].coset_rep(t))
+            };
+            match u {
+                // u fixes the base points before i, so they stay in place
+                Some(u) => {
+                    self.gens = self.gens.iter().map(|g| g.conjugate(&u)).collect();
+                    self.base = self.base.iter().map(|&b| u.apply(b)).collect();
+                }
+                None => {
+                    let j = self.base.iter().position(|&b| b == t).unwrap();
+                    self.base.remove(j);
+                    self.base.insert(i, t);
+                    self.is_sgs = false;
+                    self.words = None;
+                }
+            }
+        }
+        if !self.is_sgs {
+            self.complete_sgs(&order);
+        }
+    }
+
+    // add strong generators until the chain for the current base has the
+    // given order, by sifting random elements
+    #[cfg(feature = "rand")]
+    fn complete_sgs(&mut self, order: &BigUint) {
+        let mut grng = GroupRNG::new(self);
+        while self.order_big() != *order {
+            let chain = self.gen_stab_chain(&self.base);
+            let stripped = self.strip(&chain, &grng.draw());
+            if !stripped.is_identity() {
+                self.gens.push(stripped);
+            }
         }
-        return stab_chain;
+        self.is_sgs = true;
+    }
+
+    // without randomness, fall back to the deterministic algorithm, which
+    // starts from the generators that are already strong
+    #[cfg(not(feature = "rand"))]
+    fn complete_sgs(&mut self, order: &BigUint) {
+        self.schreier_sims();
+        debug_assert_eq!(self.order_big(), *order);
     }
 
     /// Expand the generating set of this group to include strong generators
-    pub fn random_schreier_sims(&mut self) -> () {
+    #[cfg(feature = "rand")]
+    pub fn random_schreier_sims(&mut self) -> SchreierSimsStats {
         let mut count = 0;
+        let mut stats = SchreierSimsStats { generators_added: 0, iterations: 0 };
 
         let mut grng = GroupRNG::new(self);
-        let base : Vec<usize> = (0..self.n).collect();
+        let base = self.base.clone();
         // generate new candidate schreier vectors until 4 in a row do not strip
         // to new generators
         while count < self.n {
@@ -225,168 +1010,2327 @@ impl Group {
             // the group
             let rnd = grng.draw();
             let stripped = self.strip(&stab_chain, &rnd);
-            if stripped != *self.id() {
+            stats.iterations += 1;
+            if !stripped.is_identity() {
                 self.gens.push(stripped);
-                println!("new generator found, total generators: {}", self.gens.len());
+                stats.generators_added += 1;
                 count = 0;
             } else {
                 count += 1;
             }
         }
+        stats
     }
 
     /// Compute the size of the group
-    /// Assumes the group is generated by an SGS
+    /// Assumes the group is generated by an SGS; panics if the order does not
+    /// fit in a usize (e.g. the Rubik's group), see `order_big`
     pub fn sz(&self) -> usize {
-        let base : Vec<usize> = (0..self.n).collect();
-        let chain = self.gen_stab_chain(&base);
-        return chain.iter().map(|x| x.base_orbit_size()).product()
+        let chain = self.gen_stab_chain(&self.base);
+        chain.iter().try_fold(1_usize, |acc, x| acc.checked_mul(x.base_orbit_size()))
+            .expect("group order overflows usize, use order_big")
     }
 
-    pub fn new(gens: Vec<Perm>) -> Group {
-        assert!(gens.len() > 0);
-        let l = gens[0].len();
-        for i in 0..gens.len() {
-            assert_eq!(l, gens[i].len());
-        }
-        return Group {
-            gens: gens,
-            n: l,
-            id: Perm::id(l),
-        }
+    /// Compute the size of the group without overflow
+    /// Assumes the group is generated by an SGS
+    pub fn order_big(&self) -> BigUint {
+        let chain = self.gen_stab_chain(&self.base);
+        chain.iter().map(|x| BigUint::from(x.base_orbit_size())).product()
     }
 
-
-    pub fn orbit(&self, p: usize) -> HashSet<usize> {
-        let mut frontier: Vec<usize> = Vec::new();
-        let mut orbit: HashSet<usize> = HashSet::new();
-        // invariant: frontier contains points that are not in the orbit yet
-        frontier.push(p);
-        while !frontier.is_empty() {
-            let top = frontier.pop().unwrap();
-            orbit.insert(top);
-            // build out set of points not reached yet
-            for perm in self.gens.iter() {
-                let newpoint = perm.apply(top);
-                if !orbit.contains(&newpoint) {
-                    frontier.push(newpoint);
-                }
-            }
+    /// Compute the size of the group, first computing a strong generating
+    /// set if one is not already known
+    pub fn order(&mut self) -> BigUint {
+        if !self.is_sgs {
+            self.schreier_sims();
         }
-        return orbit;
+        self.order_big()
     }
 
-    pub fn cyclic_group(n: usize) -> Group {
-        let mut gen : Vec<usize> = (1..(n)).collect();
-        gen.push(0);
-        return Group {
-            n,
-            id: Perm::id(n),
-            gens: vec![Perm::new(gen)]
-        }
+    /// Test whether g is in the group by sifting it through the stabilizer
+    /// chain: g is a member iff it strips all the way down to the identity
+    /// Assumes the group is generated by an SGS
+    pub fn in_group(&self, g: &Perm) -> bool {
+        if g.len() != self.n { return false }
+        let chain = self.gen_stab_chain(&self.base);
+        self.strip(&chain, g).is_identity()
     }
 
-    pub fn symmetric(n: usize) -> Group {
-        let mut gens = Vec::new();
-        for i in 0..n {
-            for j in (i+1)..n {
-                gens.push(Perm::new_cyc(n, vec![vec![i, j]]))
-            }
-        }
-        return Group {
-            n,
-            id: Perm::id(n),
-            gens
+    /// The pointwise stabilizer of the given points. The strong generators
+    /// relative to a base starting with `points` that fix all of them
+    /// generate the stabilizer (and are an SGS for it)
+    pub fn stabilizer_of_points(&mut self, points: &[usize]) -> Group {
+        self.schreier_sims_with_base(points);
+        let gens : Vec<Perm> = self.gens.iter()
+            .filter(|g| points.iter().all(|&p| g.apply(p) == p))
+            .cloned().collect();
+        if gens.is_empty() {
+            return Group::trivial(self.n);
         }
+        let mut r = Group::new(gens);
+        r.base = self.base.clone();
+        r.is_sgs = true;
+        r
     }
 
-    pub fn rubiks() -> Group {
-        let gens = vec![ 
-            Perm::new_cyc(49, vec![vec![ 1, 3, 8, 6], vec![ 2, 5, 7, 4], vec![ 9,33,25,17], vec![10,34,26,18], vec![11,35,27,19]]),
-            Perm::new_cyc(49, vec![vec![ 9,11,16,14], vec![10,13,15,12], vec![ 1,17,41,40], vec![ 4,20,44,37], vec![ 6,22,46,35]]),
-            Perm::new_cyc(49, vec![vec![17,19,24,22], vec![18,21,23,20], vec![ 6,25,43,16], vec![ 7,28,42,13], vec![ 8,30,41,11]]),
-            Perm::new_cyc(49, vec![vec![25,27,32,30], vec![26,29,31,28], vec![ 3,38,43,19], vec![ 5,36,45,21], vec![ 8,33,48,24]]),
-            Perm::new_cyc(49, vec![vec![33,35,40,38], vec![34,37,39,36], vec![ 3, 9,46,32], vec![ 2,12,47,29], vec![ 1,14,48,27]]),
-            Perm::new_cyc(49, vec![vec![41,43,48,46], vec![42,45,47,44], vec![14,22,30,38], vec![15,23,31,39], vec![16,24,32,40]])
-            ];
-        return Group {n: 49, id: Perm::id(49), gens}
+    /// The size of the basic orbit at each level of the stabilizer chain for
+    /// a base beginning with the given points, with the remaining points
+    /// appended in order as in `schreier_sims_with_base`; their product is
+    /// the order of the group
+    pub fn base_orbit_sizes(&mut self, base: &[usize]) -> Vec<usize> {
+        self.schreier_sims_with_base(base);
+        self.gen_stab_chain(&self.base).iter().map(|s| s.base_orbit_size()).collect()
     }
-}
+
+    /// The largest k such that the group is transitive on ordered k-tuples
+    /// of distinct points. The group is k-transitive iff it is transitive
+    /// and the stabilizer of a point is (k-1)-transitive on the remaining
+    /// points, so this walks down the stabilizer chain for the base 0, 1, ...
+    /// and checks that each basic orbit is everything not yet fixed
+    pub fn transitivity_degree(&mut self) -> usize {
+        let n = self.n;
+        let base : Vec<usize> = (0..n).collect();
+        self.schreier_sims_with_base(&base);
+        let chain = self.gen_stab_chain(&self.base);
+        chain.iter().enumerate()
+            .take_while(|(i, s)| s.base_orbit_size() == n - i)
+            .count()
+    }
+
+    /// Test whether the group has order 1
+    pub fn is_trivial(&mut self) -> bool {
+        self.order() == BigUint::from(1_u32)
+    }
+
+    // the coset representatives at each level of the stabilizer chain
+    fn transversals(&self) -> Vec<Vec<Perm>> {
+        let chain = self.gen_stab_chain(&self.base);
+        chain.iter()
+            .map(|s| (0..self.n).filter(|&p| s.schreier[p].is_some()).map(|p| s.coset_rep(p)).collect())
+            .collect()
+    }
+
+    /// Backtrack search for the subgroup of elements satisfying `property`,
+    /// which must hold on a subgroup (be closed under multiplication and
+    /// inverses). Every element is written as u_0 u_1 ... u_k over the
+    /// transversals, and the partial products are visited depth first in
+    /// increasing order of their images of the base points, taken in the
+    /// order of the base, so the identity branch comes first. Two prunings
+    /// use the subgroup K found so far:
+    ///
+    /// * when a branch with a non-identity product g at level l is reached,
+    ///   every element of the stabilizer of the first l base points
+    ///   satisfying the property has been found, so the elements below g
+    ///   satisfying it are all in K as soon as one of them is, and the
+    ///   branch is skipped if g is
+    /// * only the element of each coset Kg with the least base images needs
+    ///   to be visited, so a branch whose newest base image can be made
+    ///   smaller by an element of K fixing the earlier base images is
+    ///   skipped: that smaller element of the coset was visited before it
+    ///
+    /// The property itself is only tested on whole elements; to prune by it
+    /// at each level, describe it with `subgroup_search_refined`
+    pub fn subgroup_search<F: Fn(&Perm) -> bool>(&mut self, property: F) -> Group {
+        self.backtrack(&[], |_, _| true, property)
+    }
+
+    /// Like `subgroup_search`, with an extra test to cut the search short: a
+    /// partial product u_0 ... u_l already has its final images on the
+    /// first l + 1 base points, so `prune` is given those base points and
+    /// the partial product and returns false when no element below it can
+    /// satisfy the property
+    pub fn subgroup_search_pruned<P, F>(&mut self, prune: P, property: F) -> Group
+        where P: Fn(&[usize], &Perm) -> bool, F: Fn(&Perm) -> bool {
+        self.backtrack(&[], prune, property)
+    }
+
+    /// Like `subgroup_search`, pruned at every level by partition
+    /// backtracking in the manner of Leon. Two ordered partitions of the
+    /// points are kept: one in which the base points so far are split off
+    /// into cells of their own, and one in which their images are. Both are
+    /// refined by `refiners`, which hold for every element with the
+    /// property, so such an element maps each cell of the first partition
+    /// onto the matching cell of the second. A branch is abandoned as soon
+    /// as its newest base image is not in the cell matching that of its base
+    /// point, or the two partitions refine to different cell sizes
+    pub fn subgroup_search_refined<F>(&mut self, refiners: &[Refiner], property: F) -> Group
+        where F: Fn(&Perm) -> bool {
+        self.backtrack(refiners, |_, _| true, property)
+    }
+
+    fn backtrack<P, F>(&mut self, refiners: &[Refiner], prune: P, property: F) -> Group
+        where P: Fn(&[usize], &Perm) -> bool, F: Fn(&Perm) -> bool {
+        if !self.is_sgs {
+            self.schreier_sims();
+        }
+        let transversals = self.transversals();
+        // points are ordered by their position in the base
+        let mut rank = vec![0; self.n];
+        for (i, &b) in self.base.iter().enumerate() {
+            rank[b] = i;
+        }
+        // the partition with the first l base points split off, for each l.
+        // When the labels leave a single cell and there is nothing to commute
+        // with, the partitions never rule anything out
+        let mut root = Partition::new(self.n);
+        root.refine(refiners, true);
+        let refining = root.cells.len() > 1 || refiners.iter().any(|r| matches!(r, Refiner::Centralizes(_)));
+        let mut lefts = vec![root.clone()];
+        if refining {
+            for &b in self.base[..transversals.len()].iter() {
+                let mut next = lefts.last().unwrap().clone();
+                next.individualize(b);
+                next.refine(refiners, false);
+                lefts.push(next);
+            }
+        }
+        let mut found = Group::trivial(self.n);
+        // the elements of `found` fixing the base images of each branch
+        // expanded so far; cleared whenever `found` grows
+        let mut stabs : HashMap<Vec<usize>, Option<Rc<Group>>> = HashMap::new();
+        // each entry also records whether all of u_0 ... u_l are identities,
+        // and the partition with the images of the first l base points split
+        // off
+        let mut stack = vec![(0, self.id.clone(), true, root)];
+        while let Some((level, g, on_id, right)) = stack.pop() {
+            if level == transversals.len() {
+                if property(&g) && !found.contains(&g) {
+                    let mut gens = found.gens.clone();
+                    gens.retain(|x| !x.is_identity());
+                    gens.push(g);
+                    found = Group::new(gens);
+                    found.schreier_sims();
+                    stabs.clear();
+                }
+                continue
+            }
+            if !on_id && found.contains(&g) {
+                continue
+            }
+            // the least rank in the orbit of each point under the elements of
+            // `found` fixing the base images so far
+            let images : Vec<usize> = self.base[..level].iter().map(|&b| g.apply(b)).collect();
+            let mut least_rank = rank.clone();
+            if let Some(stab) = fixing_images(&found, &mut stabs, &images) {
+                for orbit in stab.all_orbits() {
+                    let m = orbit.iter().map(|&p| rank[p]).min().unwrap();
+                    for &p in orbit.iter() {
+                        least_rank[p] = m;
+                    }
+                }
+            }
+            let b = self.base[level];
+            let mut children : Vec<(Perm, bool, Partition)> = Vec::new();
+            for u in transversals[level].iter() {
+                let h = g.compose(u);
+                let q = h.apply(b);
+                if least_rank[q] != rank[q] { continue }
+                if refining && right.cell_of[q] != lefts[level].cell_of[b] { continue }
+                if !prune(&self.base[..=level], &h) { continue }
+                let mut next = right.clone();
+                if refining {
+                    next.individualize(q);
+                    next.refine(refiners, false);
+                    if next.shape() != lefts[level + 1].shape() { continue }
+                }
+                children.push((h, on_id && u.is_identity(), next));
+            }
+            // push the largest images first so that the least is searched first
+            children.sort_by_key(|(h, _, _)| std::cmp::Reverse(rank[h.apply(b)]));
+            for (h, is_id, next) in children {
+                stack.push((level + 1, h, is_id, next));
+            }
+        }
+        found
+    }
+
+    /// The setwise stabilizer of `set`: the subgroup of elements mapping the
+    /// set onto itself, found by a partition backtrack that keeps the points
+    /// of the set apart from the rest
+    pub fn setwise_stabilizer(&mut self, set: &[usize]) -> Group {
+        let mut in_set = vec![0; self.n];
+        for &p in set.iter() {
+            in_set[p] = 1;
+        }
+        let refiners = [Refiner::Labels(in_set.clone())];
+        self.subgroup_search_refined(&refiners, |g| set.iter().all(|&p| in_set[g.apply(p)] == 1))
+    }
+
+    /// The centralizer of g: the subgroup of elements commuting with g. A
+    /// commuting element maps each cycle of g onto a cycle of the same
+    /// length and satisfies x(g(b)) = g(x(b)), so the partition backtrack
+    /// refines by cycle lengths and by g. Panics if g is not in the group
+    pub fn centralizer(&mut self, g: &Perm) -> Group {
+        assert!(self.contains(g), "element is not in the group");
+        let refiners = [Refiner::Labels(cycle_lengths(g)), Refiner::Centralizes(g)];
+        self.subgroup_search_refined(&refiners, |x| x.compose(g) == g.compose(x))
+    }
+
+    /// The normalizer of a subgroup H: the elements g with g H g^-1 = H.
+    /// Since the conjugate has the same order as H, it is enough that g
+    /// conjugates each generator of H into H, which is the property the
+    /// partition backtrack tests, refining by the sizes of the orbits of H.
+    /// Panics if H is not contained in the group
+    pub fn normalizer(&mut self, subgroup: &mut Group) -> Group {
+        assert!(subgroup.is_subgroup_of(self), "not a subgroup");
+        if !subgroup.is_sgs {
+            subgroup.schreier_sims();
+        }
+        let subgroup : &Group = subgroup;
+        // g permutes the orbits of H, so it keeps the size of each orbit
+        let mut sizes = vec![0; self.n];
+        for orbit in subgroup.all_orbits() {
+            for &p in orbit.iter() {
+                sizes[p] = orbit.len();
+            }
+        }
+        let refiners = [Refiner::Labels(sizes)];
+        self.subgroup_search_refined(&refiners, |x| subgroup.gens.iter().all(|h| subgroup.in_group(&h.conjugate(x))))
+    }
+
+    /// Test whether this group is contained in `other`, which holds iff every
+    /// generator sifts through the stabilizer chain of `other`
+    pub fn is_subgroup_of(&self, other: &mut Group) -> bool {
+        self.n == other.n && self.gens.iter().all(|g| other.contains(g))
+    }
+
+    /// Test whether the two generating sets generate the same group on the
+    /// same points, i.e. each group is a subgroup of the other
+    pub fn equals(&mut self, other: &mut Group) -> bool {
+        self.is_subgroup_of(other) && other.is_subgroup_of(self)
+    }
+
+    /// One representative of each right coset Hg of the subgroup H in the
+    /// group. The cosets are found by multiplying the representatives found
+    /// so far on the right by the generators, keeping each product that is
+    /// not in a known coset, until no new cosets appear
+    pub fn right_cosets_of(&mut self, subgroup: &mut Group) -> Vec<Perm> {
+        assert!(subgroup.is_subgroup_of(self), "not a subgroup");
+        let mut reps = vec![self.id.clone()];
+        let mut i = 0;
+        while i < reps.len() {
+            for s in self.gens.iter() {
+                let c = reps[i].compose(s);
+                // Hc = Hr iff c r^-1 is in H
+                if !reps.iter().any(|r| subgroup.contains(&c.compose(&r.inv()))) {
+                    reps.push(c);
+                }
+            }
+            i += 1;
+        }
+        reps
+    }
+
+    /// The conjugate group x G x^-1, generated by the conjugates of the
+    /// generators; the trivial group if there are none
+    pub fn conjugate_by(&self, x: &Perm) -> Group {
+        assert_eq!(x.len(), self.n, "cannot conjugate by {} of the wrong degree", x);
+        if self.gens.is_empty() {
+            return Group::trivial(self.n);
+        }
+        Group::new(self.gens.iter().map(|g| g.conjugate(x)).collect())
+    }
+
+    /// The index [G : H] = |G| / |H| of a subgroup H of this group G
+    /// Panics if `subgroup` is not contained in this group
+    pub fn index_of(&mut self, subgroup: &mut Group) -> BigUint {
+        assert!(subgroup.is_subgroup_of(self), "not a subgroup");
+        self.order() / subgroup.order()
+    }
+
+    /// The intersection of two groups on the same points, found by a
+    /// partition backtrack over this group for elements of `other`, which
+    /// refines by the orbits of `other`
+    pub fn intersection(&mut self, other: &mut Group) -> Group {
+        assert_eq!(self.n, other.n, "groups act on different numbers of points");
+        if !other.is_sgs {
+            other.schreier_sims();
+        }
+        let other : &Group = other;
+        // an element of `other` keeps each of its orbits in place
+        let mut orbit_of = vec![0; self.n];
+        for (i, orbit) in other.all_orbits().iter().enumerate() {
+            for &p in orbit.iter() {
+                orbit_of[p] = i;
+            }
+        }
+        let refiners = [Refiner::Labels(orbit_of)];
+        self.subgroup_search_refined(&refiners, |x| other.in_group(x))
+    }
+
+    /// Test whether the group is abelian, which holds iff every pair of
+    /// generators commutes
+    pub fn is_abelian(&self) -> bool {
+        for (i, a) in self.gens.iter().enumerate() {
+            for b in self.gens[(i+1)..].iter() {
+                if a.compose(b) != b.compose(a) { return false }
+            }
+        }
+        true
+    }
+
+    /// The normal closure of `elems`: the smallest normal subgroup
+    /// containing them. Conjugates of the subgroup's generators by the
+    /// group's generators are added until none of them is new, using the
+    /// membership test to avoid adding redundant generators
+    pub fn normal_closure(&mut self, elems: &[Perm]) -> Group {
+        for x in elems.iter() {
+            assert_eq!(x.len(), self.n);
+        }
+        let gens : Vec<Perm> = elems.iter().filter(|x| !x.is_identity()).cloned().collect();
+        if gens.is_empty() {
+            return Group::trivial(self.n);
+        }
+        let mut h = Group::new(gens.clone());
+        let mut frontier = gens;
+        while let Some(x) = frontier.pop() {
+            for g in self.gens.iter() {
+                let y = x.conjugate(g);
+                if !h.contains(&y) {
+                    h.gens.push(y.clone());
+                    h.original_gens.push(y.clone());
+                    h.is_sgs = false;
+                    h.words = None;
+                    frontier.push(y);
+                }
+            }
+        }
+        h
+    }
+
+    /// The derived subgroup: the normal closure of the commutators
+    /// [a, b] = a b a^-1 b^-1 of the generators
+    pub fn derived_subgroup(&mut self) -> Group {
+        let mut comms = Vec::new();
+        for (i, a) in self.gens.iter().enumerate() {
+            for b in self.gens[(i+1)..].iter() {
+                comms.push(a.commutator(b));
+            }
+        }
+        self.normal_closure(&comms)
+    }
+
+    /// The center of the group: the elements commuting with every generator,
+    /// found by a partition backtrack refining by each generator
+    pub fn center(&mut self) -> Group {
+        let gens = self.gens.clone();
+        let refiners : Vec<Refiner> = gens.iter().map(Refiner::Centralizes).collect();
+        self.subgroup_search_refined(&refiners, |x| gens.iter().all(|g| x.compose(g) == g.compose(x)))
+    }
+
+    /// Replace the generators by at most n - 1 generators of the same group
+    /// using Jerrum's filter. The result is in general no longer a strong
+    /// generating set. The original generators are kept, so `factor` still
+    /// writes words in them
+    pub fn reduce_generators(&mut self) {
+        let mut edges = Vec::new();
+        for g in self.gens.iter() {
+            jerrum_insert(&mut edges, g.clone());
+        }
+        self.gens = edges.into_iter().map(|(_, _, g)| g).collect();
+        if self.gens.is_empty() {
+            self.gens.push(self.id.clone());
+        }
+        self.is_sgs = false;
+        self.words = None;
+    }
+
+    /// Iterate over every element of the group, first computing a strong
+    /// generating set if one is not already known
+    pub fn elements(&mut self) -> GroupElementIter {
+        if !self.is_sgs {
+            self.schreier_sims();
+        }
+        let transversals : Vec<Vec<Perm>> = self.transversals().into_iter()
+            .filter(|t| t.len() > 1)
+            .collect();
+        GroupElementIter {
+            idx: vec![0; transversals.len()],
+            transversals,
+            done: false,
+            id: self.id.clone(),
+        }
+    }
+
+    /// The stabilizer chain for the current base, first computing a strong
+    /// generating set if one is not already known
+    pub fn stabilizer_chain(&mut self) -> StabilizerChain<'_> {
+        if !self.is_sgs {
+            self.schreier_sims();
+        }
+        StabilizerChain { base: self.base.clone(), levels: self.gen_stab_chain(&self.base) }
+    }
+
+    /// Write p as a product of the generators the group was constructed
+    /// with, `original_gens()`: the returned indices i_0, i_1, ... satisfy
+    /// gens[i_0] * gens[i_1] * ... == p, even after strong generators have
+    /// been added to `get_gens()`. The words are recorded along the
+    /// transversals while building a strong generating set, which is done
+    /// on the first call and kept for later ones while the base is
+    /// unchanged. The words are valid but far from shortest, and their
+    /// length grows quickly with the length of the chain; this is only
+    /// practical for small groups. Returns None if p is not in the group
+    pub fn factor(&mut self, p: &Perm) -> Option<Vec<usize>> {
+        if p.len() != self.n { return None }
+        let chain = match &self.words {
+            Some(chain) if chain.base == self.base => chain.clone(),
+            _ => {
+                let chain = Arc::new(WordChain::new(&self.original_gens, &self.base));
+                self.words = Some(chain.clone());
+                chain
+            }
+        };
+        let (r, w) = chain.sift(0, p, &Vec::new());
+        if !r.is_identity() { return None }
+        // the word of the residue is the inverse of the word for p; inverses
+        // are written as powers since the generators have finite order
+        let mut word = Vec::new();
+        for (g, inv) in word_inv(&w) {
+            let k = if inv { self.original_gens[g].order() - 1 } else { 1 };
+            word.extend(std::iter::repeat_n(g, k));
+        }
+        Some(word)
+    }
+
+    /// Test whether p is in the group, first computing a strong generating
+    /// set if one is not already known
+    pub fn contains(&mut self, p: &Perm) -> bool {
+        if p.len() != self.n { return false }
+        if !self.is_sgs {
+            self.schreier_sims();
+        }
+        self.in_group(p)
+    }
+
+    /// Draw a random element of the group using the product replacement
+    /// algorithm. The distribution is only approximately uniform; use
+    /// `random_element_seeded` for reproducible draws
+    #[cfg(feature = "rand")]
+    pub fn random_element(&self) -> Perm {
+        GroupRNG::new(self).draw()
+    }
+
+    /// Draw a random element of the group using the product replacement
+    /// algorithm, with randomness from the given rng
+    #[cfg(feature = "rand")]
+    pub fn random_element_seeded(&self, rng: &mut impl Rng) -> Perm {
+        GroupRNG::with_rng(self, rng).draw()
+    }
+
+    /// Draw an exactly uniform element: every element is uniquely a product
+    /// of one coset representative per level of the stabilizer chain, so
+    /// choosing each uniformly gives a uniform product
+    #[cfg(feature = "rand")]
+    pub fn uniform_random_element(&mut self, rng: &mut impl Rng) -> Perm {
+        if !self.is_sgs {
+            self.schreier_sims();
+        }
+        let chain = self.gen_stab_chain(&self.base);
+        let mut r = self.id.clone();
+        for s in chain.iter() {
+            let orbit : Vec<usize> = (0..self.n).filter(|&p| s.schreier[p].is_some()).collect();
+            let p = orbit[rng.gen_range(0..orbit.len())];
+            r = r.compose(&s.coset_rep(p));
+        }
+        r
+    }
+
+    /// The number of orbits of the group on the k-subsets of its points, by
+    /// Burnside's lemma: the average over all elements of the number of
+    /// k-subsets each fixes. Enumerates the whole group, so it is only
+    /// practical for small groups
+    pub fn num_orbits_on_subsets(&mut self, k: usize) -> BigUint {
+        let total : BigUint = self.elements().map(|g| {
+            // a fixed subset is a union of cycles, so count the ways to
+            // pick cycles whose lengths sum to k
+            let mut lens : Vec<usize> = g.cycles().iter().map(|c| c.len()).collect();
+            lens.resize(lens.len() + g.fixed_points().len(), 1);
+            let mut ways = vec![BigUint::from(0_u32); k + 1];
+            ways[0] = BigUint::from(1_u32);
+            for l in lens {
+                for j in (l..=k).rev() {
+                    let w = ways[j - l].clone();
+                    ways[j] += w;
+                }
+            }
+            ways[k].clone()
+        }).sum();
+        total / self.order()
+    }
+
+    /// The cycle index of the group, averaging the cycle type of every
+    /// element. Enumerates the whole group, so it is only practical for
+    /// small groups
+    pub fn cycle_index(&mut self) -> CycleIndex {
+        let n = self.n;
+        let mut counts : BTreeMap<Vec<usize>, BigInt> = BTreeMap::new();
+        for g in self.elements() {
+            let mut exps = vec![0; n];
+            for l in g.cycle_type() {
+                exps[l - 1] += 1;
+            }
+            let len = exps.iter().rposition(|&e| e != 0).map_or(0, |i| i + 1);
+            exps.truncate(len);
+            *counts.entry(exps).or_insert_with(|| BigInt::from(0)) += 1;
+        }
+        let order = BigInt::from(self.order());
+        let terms = counts.into_iter()
+            .map(|(exps, c)| (exps, BigRational::new(c, order.clone())))
+            .collect();
+        CycleIndex { terms }
+    }
+
+    /// The multiplication table of the group: the list of its elements and
+    /// a table whose entry [i][j] is the index of elements[i] * elements[j].
+    /// Panics if the order exceeds `CAYLEY_TABLE_MAX_ORDER`
+    pub fn cayley_table(&mut self) -> (Vec<Perm>, Vec<Vec<usize>>) {
+        self.cayley_table_with_limit(CAYLEY_TABLE_MAX_ORDER)
+    }
+
+    /// Like `cayley_table`, but panics if the order exceeds `max_order`
+    pub fn cayley_table_with_limit(&mut self, max_order: usize) -> (Vec<Perm>, Vec<Vec<usize>>) {
+        let order = self.order();
+        assert!(order <= BigUint::from(max_order),
+                "group of order {} is too large for a Cayley table", order);
+        let elements : Vec<Perm> = self.elements().collect();
+        let index : HashMap<&Perm, usize> = elements.iter().enumerate().map(|(i, x)| (x, i)).collect();
+        let table = elements.iter()
+            .map(|x| elements.iter().map(|y| index[&x.compose(y)]).collect())
+            .collect();
+        (elements, table)
+    }
+
+    /// The exponent of the group, the least common multiple of the orders
+    /// of its elements. Enumerates the whole group, so it is only practical
+    /// for small groups
+    pub fn exponent(&mut self) -> usize {
+        self.elements().fold(1, |e, g| lcm(e, g.order()))
+    }
+
+    /// The number of elements of each order. Enumerates the whole group, so
+    /// it is only practical for small groups
+    pub fn order_statistics(&mut self) -> BTreeMap<usize, BigUint> {
+        let mut counts : BTreeMap<usize, BigUint> = BTreeMap::new();
+        for g in self.elements() {
+            *counts.entry(g.order()).or_insert_with(|| BigUint::from(0_u32)) += 1_u32;
+        }
+        counts
+    }
+
+    /// An element of largest order together with that order. Enumerates the
+    /// whole group, so it is only practical for small groups
+    pub fn max_order_element(&mut self) -> (Perm, usize) {
+        let mut best = (self.id.clone(), 1);
+        for g in self.elements() {
+            let o = g.order();
+            if o > best.1 {
+                best = (g, o);
+            }
+        }
+        best
+    }
+
+    /// The conjugacy classes of the group. Each class is the orbit of an
+    /// element under conjugation by the generators, so classes are grown
+    /// from unclassified elements rather than testing every pair.
+    /// Enumerates the whole group, so it is only practical for small groups
+    pub fn conjugacy_classes(&mut self) -> Vec<Vec<Perm>> {
+        let mut seen : HashSet<Perm> = HashSet::new();
+        let mut classes = Vec::new();
+        for x in self.elements() {
+            if seen.contains(&x) {
+                continue;
+            }
+            seen.insert(x.clone());
+            let mut class = vec![x];
+            let mut i = 0;
+            while i < class.len() {
+                for g in self.gens.iter() {
+                    let y = class[i].conjugate(g);
+                    if seen.insert(y.clone()) {
+                        class.push(y);
+                    }
+                }
+                i += 1;
+            }
+            classes.push(class);
+        }
+        classes
+    }
+
+    /// The subgroup generated by the given elements of the group, which
+    /// must have its degree; the trivial group if there are none
+    pub fn subgroup(&self, elements: Vec<Perm>) -> Group {
+        for g in elements.iter() {
+            assert_eq!(g.len(), self.n, "element {} has the wrong degree", g);
+        }
+        if elements.is_empty() {
+            return Group::trivial(self.n);
+        }
+        Group::new(elements)
+    }
+
+    /// Like `new`, but returns an error rather than panicking when the list
+    /// of generators is empty or their degrees differ
+    pub fn from_generators_checked(gens: Vec<Perm>) -> Result<Group, GroupError> {
+        let expected = gens.first().ok_or(GroupError::NoGenerators)?.len();
+        if let Some((index, g)) = gens.iter().enumerate().find(|(_, g)| g.len() != expected) {
+            return Err(GroupError::DegreeMismatch { index, expected, found: g.len() });
+        }
+        Ok(Group::new(gens))
+    }
+
+    pub fn new(gens: Vec<Perm>) -> Group {
+        assert!(!gens.is_empty());
+        let l = gens[0].len();
+        for g in gens.iter() {
+            assert_eq!(l, g.len());
+        }
+        Group {
+            original_gens: gens.clone(),
+            gens,
+            n: l,
+            id: Perm::id(l),
+            base: (0..l).collect(),
+            is_sgs: false,
+            words: None,
+        }
+    }
+
+
+    /// Parse a group of the given degree written as GAP code, e.g.
+    /// `Group((1,2,3),(1,2))`. GAP numbers points from 1, so every point is
+    /// shifted down by one
+    pub fn from_gap(degree: usize, s: &str) -> Result<Group, ParseError> {
+        let body = s.trim().strip_prefix("Group")
+            .map(|r| r.trim_start())
+            .and_then(|r| r.strip_prefix('('))
+            .and_then(|r| r.trim_end().strip_suffix(')'))
+            .ok_or_else(|| ParseError::Syntax("expected Group(...)".to_string()))?;
+        // generators are separated by the commas outside of any cycle
+        let mut pieces = Vec::new();
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in body.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => return Err(ParseError::Syntax("unbalanced ')'".to_string())),
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    pieces.push(&body[start..i]);
+                    start = i + 1;
+                }
+                _ => ()
+            }
+        }
+        pieces.push(&body[start..]);
+        if pieces.len() == 1 && pieces[0].trim().is_empty() {
+            return Ok(Group::trivial(degree));
+        }
+        let gens = pieces.iter().enumerate()
+            .map(|(index, g)| Perm::parse_from(degree, g, 1)
+                 .map_err(|error| ParseError::Generator { index, error }))
+            .collect::<Result<Vec<Perm>, ParseError>>()?;
+        Ok(Group::new(gens))
+    }
+
+    /// Write the generators as GAP code in 1-based cycle notation, the
+    /// inverse of `from_gap`
+    pub fn to_gap(&self) -> String {
+        let gens : Vec<String> = self.gens.iter().map(|g| {
+            let cycles = g.cycles();
+            if cycles.is_empty() {
+                return "()".to_string();
+            }
+            cycles.iter().map(|c| {
+                let pts : Vec<String> = c.iter().map(|x| (x + 1).to_string()).collect();
+                format!("({})", pts.join(","))
+            }).collect()
+        }).collect();
+        format!("Group({})", gens.join(","))
+    }
+
+    pub fn orbit(&self, p: usize) -> HashSet<usize> {
+        let mut frontier: Vec<usize> = Vec::new();
+        let mut orbit: HashSet<usize> = HashSet::new();
+        // invariant: frontier contains points that are not in the orbit yet
+        frontier.push(p);
+        while let Some(top) = frontier.pop() {
+            orbit.insert(top);
+            // build out set of points not reached yet
+            for perm in self.gens.iter() {
+                let newpoint = perm.apply(top);
+                if !orbit.contains(&newpoint) {
+                    frontier.push(newpoint);
+                }
+            }
+        }
+        orbit
+    }
+
+    /// Compute the orbit of p like `orbit`, but expand each breadth-first
+    /// layer of the search in parallel
+    #[cfg(feature = "rayon")]
+    pub fn orbit_par(&self, p: usize) -> HashSet<usize> {
+        use rayon::prelude::*;
+        let mut orbit: HashSet<usize> = HashSet::new();
+        orbit.insert(p);
+        let mut frontier = vec![p];
+        while !frontier.is_empty() {
+            let images: HashSet<usize> = frontier.par_iter()
+                .flat_map_iter(|&q| self.gens.iter().map(move |g| g.apply(q)))
+                .filter(|q| !orbit.contains(q))
+                .collect();
+            frontier = images.into_iter().collect();
+            orbit.extend(frontier.iter().copied());
+        }
+        orbit
+    }
+
+    /// Compute the orbit of an unordered set of points, whose images are
+    /// the sets of images of its points
+    pub fn orbit_of_set(&self, set: &[usize]) -> HashSet<BTreeSet<usize>> {
+        let start : BTreeSet<usize> = set.iter().copied().collect();
+        let mut orbit: HashSet<BTreeSet<usize>> = HashSet::new();
+        let mut frontier = vec![start.clone()];
+        orbit.insert(start);
+        while let Some(top) = frontier.pop() {
+            for perm in self.gens.iter() {
+                let image : BTreeSet<usize> = top.iter().map(|&x| perm.apply(x)).collect();
+                if !orbit.contains(&image) {
+                    orbit.insert(image.clone());
+                    frontier.push(image);
+                }
+            }
+        }
+        orbit
+    }
+
+    /// The action of the group on the orbit of `representative`, with the
+    /// orbit points renumbered to 0..k in increasing order; this is the
+    /// image of the homomorphism restricting each element to the orbit
+    pub fn induced_on_orbit(&self, representative: usize) -> Group {
+        let mut orbit : Vec<usize> = self.orbit(representative).into_iter().collect();
+        orbit.sort_unstable();
+        Group::new(self.gens.iter().map(|g| g.restrict(&orbit).unwrap()).collect())
+    }
+
+    /// Compute the orbit of an ordered tuple of points, where the group acts
+    /// on each entry of the tuple
+    pub fn orbit_of_tuple(&self, tuple: &[usize]) -> HashSet<Vec<usize>> {
+        let mut orbit: HashSet<Vec<usize>> = HashSet::new();
+        let mut frontier = vec![tuple.to_vec()];
+        orbit.insert(tuple.to_vec());
+        while let Some(top) = frontier.pop() {
+            for perm in self.gens.iter() {
+                let image : Vec<usize> = top.iter().map(|&x| perm.apply(x)).collect();
+                if !orbit.contains(&image) {
+                    orbit.insert(image.clone());
+                    frontier.push(image);
+                }
+            }
+        }
+        orbit
+    }
+
+    /// Compute the orbit of p together with a transversal in one traversal.
+    /// For each point q in the orbit, the first vector holds the index of the
+    /// generator whose image reached q (None for p itself) and the second
+    /// holds a representative mapping p to q; both are None outside the orbit
+    pub fn orbit_schreier(&self, p: usize) -> (Vec<Option<usize>>, Vec<Option<Perm>>) {
+        let mut gen_idx = vec![None; self.n];
+        let mut reps : Vec<Option<Perm>> = vec![None; self.n];
+        reps[p] = Some(self.id.clone());
+        let mut frontier = vec![p];
+        while let Some(top) = frontier.pop() {
+            for (i, perm) in self.gens.iter().enumerate() {
+                let newpoint = perm.apply(top);
+                if reps[newpoint].is_some() { continue }
+                gen_idx[newpoint] = Some(i);
+                reps[newpoint] = Some(perm.compose(reps[top].as_ref().unwrap()));
+                frontier.push(newpoint);
+            }
+        }
+        (gen_idx, reps)
+    }
+
+    /// Test whether the group has a single orbit, stopping as soon as the
+    /// orbit of 0 is seen to contain every point
+    pub fn is_transitive(&self) -> bool {
+        if self.n == 0 { return true }
+        let mut visited = vec![false; self.n];
+        let mut count = 1;
+        let mut frontier = vec![0];
+        visited[0] = true;
+        while let Some(top) = frontier.pop() {
+            if count == self.n { return true }
+            for perm in self.gens.iter() {
+                let newpoint = perm.apply(top);
+                if !visited[newpoint] {
+                    visited[newpoint] = true;
+                    count += 1;
+                    frontier.push(newpoint);
+                }
+            }
+        }
+        count == self.n
+    }
+
+    /// Partition the points into orbits; each orbit is sorted and the orbits
+    /// are ordered by their least element
+    pub fn all_orbits(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.n];
+        let mut orbits = Vec::new();
+        for p in 0..self.n {
+            if visited[p] { continue }
+            let mut orbit = vec![p];
+            let mut frontier = vec![p];
+            visited[p] = true;
+            while let Some(top) = frontier.pop() {
+                for perm in self.gens.iter() {
+                    let newpoint = perm.apply(top);
+                    if !visited[newpoint] {
+                        visited[newpoint] = true;
+                        orbit.push(newpoint);
+                        frontier.push(newpoint);
+                    }
+                }
+            }
+            orbit.sort();
+            orbits.push(orbit);
+        }
+        orbits
+    }
+
+    /// The least point of each orbit, in increasing order
+    pub fn orbit_representatives(&self) -> Vec<usize> {
+        self.all_orbits().iter().map(|o| o[0]).collect()
+    }
+
+    /// The finest block system in which a and b share a block, found with
+    /// Atkinson's algorithm: merge the blocks of a and b, then repeatedly
+    /// merge the blocks of the images of every merged pair under each
+    /// generator. Blocks are sorted and listed by least point
+    fn block_system_joining(&self, a: usize, b: usize) -> Vec<Vec<usize>> {
+        fn find(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+        let mut parent : Vec<usize> = (0..self.n).collect();
+        let mut pending = vec![(a, b)];
+        parent[b] = a;
+        while let Some((x, y)) = pending.pop() {
+            for g in self.gens.iter() {
+                let rx = find(&mut parent, g.apply(x));
+                let ry = find(&mut parent, g.apply(y));
+                if rx != ry {
+                    parent[ry] = rx;
+                    pending.push((rx, ry));
+                }
+            }
+        }
+        let mut blocks : Vec<Vec<usize>> = Vec::new();
+        let mut block_of : HashMap<usize, usize> = HashMap::new();
+        for p in 0..self.n {
+            let r = find(&mut parent, p);
+            let i = *block_of.entry(r).or_insert_with(|| { blocks.push(Vec::new()); blocks.len() - 1 });
+            blocks[i].push(p);
+        }
+        blocks
+    }
+
+    /// A nontrivial block system of a transitive group with blocks as small
+    /// as possible, or None if the group is primitive or not transitive.
+    /// Tries each block system in which 0 shares a block with another point
+    pub fn minimal_block_system(&self) -> Option<Vec<Vec<usize>>> {
+        if !self.is_transitive() { return None }
+        (1..self.n)
+            .map(|k| self.block_system_joining(0, k))
+            .filter(|blocks| blocks.len() > 1)
+            .min_by_key(|blocks| blocks[0].len())
+    }
+
+    /// Test whether the group is the full symmetric group on its points,
+    /// which is the only subgroup of S_n of order n!. Intransitive groups
+    /// are rejected before computing the order
+    pub fn is_symmetric(&mut self) -> bool {
+        if !self.is_transitive() { return false }
+        let factorial : BigUint = (1..=self.n).map(BigUint::from).product();
+        self.order() == factorial
+    }
+
+    /// Test whether the group is the alternating group on its points: its
+    /// generators are even and its order is n!/2, since A_n is the only
+    /// subgroup of S_n of index 2. For n < 3 this means the group is trivial
+    pub fn is_alternating(&mut self) -> bool {
+        if !self.gens.iter().all(|g| g.is_even()) { return false }
+        if self.n < 3 { return true }
+        if !self.is_transitive() { return false }
+        let factorial : BigUint = (1..=self.n).map(BigUint::from).product();
+        self.order() * BigUint::from(2_u32) == factorial
+    }
+
+    /// Test whether the group acts regularly: it is transitive and only the
+    /// identity fixes a point, so its order is the number of points
+    pub fn is_regular(&mut self) -> bool {
+        self.is_transitive() && self.order() == BigUint::from(self.n)
+    }
+
+    /// Test whether the group is transitive and preserves no nontrivial
+    /// block system
+    pub fn is_primitive(&self) -> bool {
+        self.is_transitive() && (1..self.n).all(|k| self.block_system_joining(0, k).len() == 1)
+    }
+
+    pub fn cyclic_group(n: usize) -> Group {
+        let mut gen : Vec<usize> = (1..(n)).collect();
+        gen.push(0);
+        let gens = vec![Perm::new(gen)];
+        Group {
+            n,
+            id: Perm::id(n),
+            original_gens: gens.clone(),
+            gens,
+            base: (0..n).collect(),
+            is_sgs: false,
+            words: None,
+        }
+    }
+
+    pub fn symmetric(n: usize) -> Group {
+        let mut gens = Vec::new();
+        for i in 0..n {
+            for j in (i+1)..n {
+                gens.push(Perm::new_cyc(n, vec![vec![i, j]]))
+            }
+        }
+        Group {
+            n,
+            id: Perm::id(n),
+            original_gens: gens.clone(),
+            gens,
+            base: (0..n).collect(),
+            is_sgs: false,
+            words: None,
+        }
+    }
+
+    /// The group generated by the permutations of the given degree with
+    /// each of the given lists of cycles
+    pub fn from_cycles(degree: usize, generators: Vec<Vec<Vec<usize>>>) -> Group {
+        Group::new(generators.into_iter().map(|c| Perm::new_cyc(degree, c)).collect())
+    }
+
+    /// The symmetric group on n points generated by just the transposition
+    /// (0 1) and the n-cycle (0 1 ... n-1); trivial for n < 2
+    pub fn symmetric_min(n: usize) -> Group {
+        if n < 2 {
+            return Group::trivial(n);
+        }
+        Group::new(vec![Perm::new_cyc(n, vec![vec![0, 1]]), Perm::new_cyc(n, vec![(0..n).collect()])])
+    }
+
+    /// The direct product of two groups acting on the disjoint union of their
+    /// points: this group acts on the first self.n points and `other` on the
+    /// following other.n points
+    pub fn direct_product(&self, other: &Group) -> Group {
+        let gens = self.gens.iter().map(|g| g.direct_sum(&other.id))
+            .chain(other.gens.iter().map(|g| self.id.direct_sum(g)))
+            .collect();
+        Group::new(gens)
+    }
+
+    /// The imprimitive wreath product of `base` by `top`, acting on
+    /// base.n * top.n points split into top.n blocks of base.n consecutive
+    /// points: a copy of `base` acts on each block, and `top` permutes the
+    /// blocks. Its order is |base|^top.n * |top|
+    pub fn wreath_product(base: &Group, top: &Group) -> Group {
+        let m = base.n;
+        let k = top.n;
+        let mut gens = Vec::new();
+        for b in 0..k {
+            for g in base.gens.iter() {
+                gens.push(Perm::id(b * m).direct_sum(g).embed(k * m));
+            }
+        }
+        for h in top.gens.iter() {
+            gens.push(Perm::new((0..(k * m)).map(|x| h.apply(x / m) * m + x % m).collect()));
+        }
+        Group::new(gens)
+    }
+
+    /// The trivial group on n points, generated by the identity
+    pub fn trivial(n: usize) -> Group {
+        Group::new(vec![Perm::id(n)])
+    }
+
+    /// The alternating group on n points, generated by the 3-cycles
+    /// (0 1 k) for 2 <= k < n; trivial for n < 3
+    pub fn alternating(n: usize) -> Group {
+        if n < 3 {
+            return Group::trivial(n);
+        }
+        Group::new((2..n).map(|k| Perm::new_cyc(n, vec![vec![0, 1, k]])).collect())
+    }
+
+    /// The dihedral group of order 2n acting on the vertices of an n-gon,
+    /// generated by the rotation i -> i + 1 and the reflection i -> -i
+    pub fn dihedral(n: usize) -> Group {
+        let rot : Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+        let refl : Vec<usize> = (0..n).map(|i| (n - i) % n).collect();
+        Group::new(vec![Perm::new(rot), Perm::new(refl)])
+    }
+
+    pub fn rubiks() -> Group {
+        Group::from_cycles(49, vec![
+            vec![vec![ 1, 3, 8, 6], vec![ 2, 5, 7, 4], vec![ 9,33,25,17], vec![10,34,26,18], vec![11,35,27,19]],
+            vec![vec![ 9,11,16,14], vec![10,13,15,12], vec![ 1,17,41,40], vec![ 4,20,44,37], vec![ 6,22,46,35]],
+            vec![vec![17,19,24,22], vec![18,21,23,20], vec![ 6,25,43,16], vec![ 7,28,42,13], vec![ 8,30,41,11]],
+            vec![vec![25,27,32,30], vec![26,29,31,28], vec![ 3,38,43,19], vec![ 5,36,45,21], vec![ 8,33,48,24]],
+            vec![vec![33,35,40,38], vec![34,37,39,36], vec![ 3, 9,46,32], vec![ 2,12,47,29], vec![ 1,14,48,27]],
+            vec![vec![41,43,48,46], vec![42,45,47,44], vec![14,22,30,38], vec![15,23,31,39], vec![16,24,32,40]]
+            ])
+    }
+}
+
+#[test]
+fn test_num_gens() {
+    let g = Group::symmetric(5);
+    assert_eq!(g.num_gens(), 10);
+    assert_eq!(g.num_points(), 5);
+}
+
+#[test]
+fn test_orb_cyclic() {
+    let g = Group::cyclic_group(5);
+    assert_eq!(g.orbit(0).len(), 5);
+}
+
+#[test]
+fn test_orb_symmetric() {
+    let g = Group::symmetric(5);
+    assert_eq!(g.orbit(0).len(), 5);
+    assert_eq!(g.orbit(1).len(), 5);
+    assert_eq!(g.orbit(2).len(), 5);
+    assert_eq!(g.orbit(3).len(), 5);
+    assert_eq!(g.orbit(4).len(), 5);
+}
+
+#[test]
+fn test_all_orbits() {
+    let g = Group::new(vec![
+        Perm::new_cyc(5, vec![vec![0, 1]]),
+        Perm::new_cyc(5, vec![vec![2, 3]]),
+    ]);
+    assert_eq!(g.all_orbits(), vec![vec![0, 1], vec![2, 3], vec![4]]);
+    assert_eq!(Group::cyclic_group(4).all_orbits(), vec![vec![0, 1, 2, 3]]);
+}
+
+#[test]
+fn test_is_transitive() {
+    assert!(Group::symmetric(5).is_transitive());
+    assert!(Group::cyclic_group(5).is_transitive());
+    assert!(!Group::new(vec![Perm::new_cyc(4, vec![vec![0, 1]])]).is_transitive());
+    assert!(Group::trivial(1).is_transitive());
+}
+
+#[test]
+fn test_orbit_schreier() {
+    let g = Group::new(vec![
+        Perm::new_cyc(6, vec![vec![0, 1]]),
+        Perm::new_cyc(6, vec![vec![1, 2, 3]]),
+    ]);
+    let (gen_idx, reps) = g.orbit_schreier(0);
+    assert_eq!(gen_idx[0], None);
+    assert_eq!(gen_idx[1], Some(0));
+    assert_eq!(gen_idx[2], Some(1));
+    for (q, rep) in reps.iter().enumerate().take(4) {
+        assert_eq!(rep.as_ref().unwrap().apply(0), q);
+    }
+    assert!(reps[4].is_none() && gen_idx[5].is_none());
+}
+
+#[test]
+fn test_repr() {
+    // start with a group that is already an SGS; this is an SGS for S4
+    let g = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![2, 3]]),
+    ]);
+
+    // generate the stabilizer chain
+    let s1 = StabView::new(&g, vec![], 0);
+
+    // two perms are in the same coset if they both map the basis point
+    // to the same point, so let's verify that fact; both these cycles map
+    // the basis point 0 to 1 
+    let rep1 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]));
+    let rep2 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 1]]));
+    let rep3 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 2]]));
+    assert_eq!(rep1, rep2);
+    assert_eq!(rep1.apply(0), 1);
+    assert_ne!(rep1, rep3);
+}
+
+#[test]
+fn test_repr_orbit() {
+    let g = Group::cyclic_group(5);
+    let s = StabView::new(&g, vec![], 0);
+    assert_eq!(s.base_orbit_size(), 5);
+
+    // in this group the schreier tree for 0 is the path 0 -> 1 -> 2 -> 3, so
+    // the representatives are words of non-commuting generators, which
+    // checks that the tree is walked in the right order
+    let g = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1]]),
+        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
+    ]);
+    let s = StabView::new(&g, vec![], 0);
+    assert_eq!(s.base_orbit_size(), 4);
+    for q in 0..4 {
+        let rep = s.repr(&Perm::new_cyc(4, vec![vec![0, q]]));
+        assert_eq!(rep.apply(0), q);
+    }
+}
+
+#[test]
+fn test_strip() {
+    // start with a group that is already an SGS; this is an SGS for S4
+    let g = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![2, 3]]),
+    ]);
+
+    // generate the stabilizer chain
+    let chain = g.gen_stab_chain(&(0..3).collect::<Vec<usize>>());
+    // let s1 = StabView::new(&g, vec![], 0);
+    // let s2 = StabView::new(&g, vec![0], 1);
+    // let s3 = StabView::new(&g, vec![0, 1], 2);
+    // let s4 = StabView::new(&g, vec![0, 1, 2], 3);
+    // let chain = vec![s1, s2, s3, s4];
+
+    // now see if we can properly strip elements of S4
+    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![0, 3]])), *g.id());
+    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![1, 2, 3]])), *g.id());
+    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![0, 2, 3]])), *g.id());
+    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![3, 1, 2]])), *g.id());
+}
+
+#[test]
+fn test_stab_chain_base() {
+    // the transpositions are an SGS for S4 relative to any base, so the
+    // chain should stabilize the base points in the order given
+    let g = Group::symmetric(4);
+    let base = vec![2, 0, 3, 1];
+    let chain = g.gen_stab_chain(&base);
+    assert_eq!(chain.len(), g.num_points());
+    for (i, s) in chain.iter().enumerate() {
+        assert_eq!(s.point, base[i]);
+        assert_eq!(s.base, base[..i].to_vec());
+        assert_eq!(s.base_orbit_size(), 4 - i);
+    }
+}
+
+#[test]
+fn test_size() {
+    // start with a group that is already an SGS; this is an SGS for S4
+    let g = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
+        Perm::new_cyc(4, vec![vec![2, 3]]),
+    ]);
+    assert_eq!(g.sz(), 4*3*2);
+}
+
+#[test]
+fn test_in_group() {
+    // an SGS for A5: the stabilizer of 0 is A4 on {1, 2, 3, 4}, generated by
+    // (1 3 4) and (2 3 4), and so on down the chain
+    let g = Group::new(vec![
+        Perm::new_cyc(5, vec![vec![0, 3, 4]]),
+        Perm::new_cyc(5, vec![vec![1, 3, 4]]),
+        Perm::new_cyc(5, vec![vec![2, 3, 4]]),
+    ]);
+    assert_eq!(g.sz(), 60);
+    assert!(g.in_group(&Perm::new_cyc(5, vec![vec![0, 1], vec![2, 3]])));
+    assert!(g.in_group(&Perm::new_cyc(5, vec![vec![0, 1, 2, 3, 4]])));
+    assert!(g.in_group(&Perm::new_cyc(5, vec![vec![4, 2, 0]])));
+    assert!(!g.in_group(&Perm::new_cyc(5, vec![vec![0, 1]])));
+    assert!(!g.in_group(&Perm::new_cyc(5, vec![vec![0, 1, 2, 3]])));
+
+    // every permutation of the right degree is in S5
+    let g = Group::symmetric(5);
+    assert!(g.in_group(&Perm::new_cyc(5, vec![vec![0, 1]])));
+    assert!(g.in_group(&Perm::new_cyc(5, vec![vec![0, 4, 1, 3]])));
+    assert!(!g.in_group(&Perm::new_cyc(6, vec![vec![0, 1]])));
+}
+
+#[test]
+fn test_schreier_sims() {
+    let mut g = Group::symmetric(6);
+    g.schreier_sims();
+    assert_eq!(g.sz(), 720);
+
+    // the same group, but from generators that are far from an SGS
+    let mut g = Group::new(vec![
+        Perm::new_cyc(6, vec![vec![0, 1]]),
+        Perm::new_cyc(6, vec![vec![0, 1, 2, 3, 4, 5]]),
+    ]);
+    g.schreier_sims();
+    assert_eq!(g.sz(), 720);
+
+    let mut g = Group::rubiks();
+    g.schreier_sims();
+    assert_eq!(g.order_big(), 43252003274489856000_u128.into());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_schreier_stats() {
+    // the transpositions already form an SGS for S8, so start from a
+    // 2-generator presentation that needs new strong generators
+    let mut g = Group::new(vec![
+        Perm::new_cyc(8, vec![vec![0, 1]]),
+        Perm::new_cyc(8, vec![vec![0, 1, 2, 3, 4, 5, 6, 7]]),
+    ]);
+    let stats = g.random_schreier_sims();
+    assert!(stats.generators_added > 0);
+    assert_eq!(g.num_gens(), 2 + stats.generators_added);
+    assert!(stats.iterations >= g.num_points());
+}
+
+#[test]
+fn test_schreier_sims_with_base() {
+    let mut g = Group::rubiks();
+    g.schreier_sims_with_base(&[40, 3]);
+    assert_eq!(&g.base[..3], &[40, 3, 0]);
+    assert_eq!(g.base.len(), 49);
+    assert_eq!(g.order_big(), 43252003274489856000_u128.into());
+
+    let mut g = Group::new(vec![
+        Perm::new_cyc(5, vec![vec![0, 1]]),
+        Perm::new_cyc(5, vec![vec![0, 1, 2, 3, 4]]),
+    ]);
+    g.schreier_sims_with_base(&[4, 2]);
+    assert_eq!(g.sz(), 120);
+}
+
+#[test]
+#[should_panic]
+fn test_schreier_sims_repeated_base() {
+    Group::symmetric(4).schreier_sims_with_base(&[1, 2, 1]);
+}
+
+#[test]
+fn test_contains() {
+    let mut g = Group::symmetric(5);
+    let mut count = 0;
+    for i in 0..3125 {
+        let v : Vec<usize> = (0..5).map(|k| (i / 5_usize.pow(k)) % 5).collect();
+        if let Ok(p) = Perm::try_new(v) {
+            assert!(g.contains(&p));
+            count += 1;
+        }
+    }
+    assert_eq!(count, 120);
+    assert!(!g.contains(&Perm::id(4)));
+
+    let mut g = Group::cyclic_group(5);
+    assert!(!g.contains(&Perm::new_cyc(5, vec![vec![0, 1]])));
+    assert!(g.contains(&Perm::new_cyc(5, vec![vec![0, 2, 4, 1, 3]])));
+    assert!(g.is_sgs);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_random_element() {
+    use rand::SeedableRng;
+    let g = Group::symmetric(4);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let seen : HashSet<Perm> = (0..1000).map(|_| g.random_element_seeded(&mut rng)).collect();
+    assert_eq!(seen.len(), 24);
+
+    let mut g = Group::cyclic_group(5);
+    let p = g.random_element();
+    assert!(g.contains(&p));
+}
+
+#[test]
+fn test_order() {
+    let mut g = Group::rubiks();
+    let expected = BigUint::from(2_u32).pow(27) * BigUint::from(3_u32).pow(14)
+        * BigUint::from(5_u32).pow(3) * BigUint::from(7_u32).pow(2) * BigUint::from(11_u32);
+    assert_eq!(g.order(), expected);
+    assert_eq!(g.order().to_string(), "43252003274489856000");
+}
+
+#[test]
+#[should_panic]
+fn test_sz_overflow() {
+    let mut g = Group::rubiks();
+    g.schreier_sims();
+    g.sz();
+}
+
+#[test]
+fn test_elements() {
+    let mut g = Group::symmetric(4);
+    let elems : Vec<Perm> = g.elements().collect();
+    assert_eq!(elems.len(), 24);
+    let seen : HashSet<Perm> = elems.into_iter().collect();
+    assert_eq!(seen.len(), 24);
+
+    let mut g = Group::new(vec![
+        Perm::new_cyc(5, vec![vec![0, 3, 4]]),
+        Perm::new_cyc(5, vec![vec![1, 3, 4]]),
+    ]);
+    let seen : HashSet<Perm> = g.elements().collect();
+    assert_eq!(BigUint::from(seen.len()), g.order());
+    assert!(seen.iter().all(|p| p.is_even() && g.in_group(p)));
+
+    let mut g = Group::cyclic_group(1);
+    assert_eq!(g.elements().collect::<Vec<Perm>>(), vec![Perm::id(1)]);
+}
+
+#[test]
+fn test_alternating() {
+    let mut g = Group::alternating(5);
+    g.schreier_sims();
+    assert_eq!(g.sz(), 60);
+    assert_eq!(Group::alternating(4).order(), BigUint::from(12_u32));
+    assert_eq!(Group::alternating(2).order(), BigUint::from(1_u32));
+    assert_eq!(Group::alternating(1).order(), BigUint::from(1_u32));
+}
+
+#[test]
+fn test_dihedral() {
+    assert_eq!(Group::dihedral(4).order(), BigUint::from(8_u32));
+    assert_eq!(Group::dihedral(5).order(), BigUint::from(10_u32));
+    assert_eq!(Group::dihedral(12).order(), BigUint::from(24_u32));
+}
+
+#[test]
+fn test_trivial() {
+    let mut g = Group::trivial(5);
+    assert!(g.is_trivial());
+    assert_eq!(g.order(), BigUint::from(1_u32));
+    assert_eq!(g.num_points(), 5);
+    assert!(!Group::cyclic_group(2).is_trivial());
+}
+
+#[test]
+fn test_stabilizer_of_points() {
+    let mut g = Group::symmetric(5);
+    let mut h = g.stabilizer_of_points(&[0, 1]);
+    assert_eq!(h.order(), BigUint::from(6_u32));
+    assert!(h.get_gens().iter().all(|p| p.apply(0) == 0 && p.apply(1) == 1));
+
+    let mut g = Group::cyclic_group(5);
+    assert!(g.stabilizer_of_points(&[3]).is_trivial());
+}
+
+#[test]
+fn test_setwise_stabilizer() {
+    // S_k x S_{n-k}, swapping within the set and within its complement
+    let mut g = Group::symmetric(4);
+    assert_eq!(g.setwise_stabilizer(&[0, 1]).order(), BigUint::from(4_u32));
+    let mut g = Group::symmetric(5);
+    let mut h = g.setwise_stabilizer(&[1, 3]);
+    assert_eq!(h.order(), BigUint::from(12_u32));
+    assert!(h.get_gens().iter().all(|p| [1, 3].contains(&p.apply(1)) && [1, 3].contains(&p.apply(3))));
+    let mut g = Group::symmetric(6);
+    assert_eq!(g.setwise_stabilizer(&[0, 2, 4]).order(), BigUint::from(36_u32));
+    assert_eq!(g.setwise_stabilizer(&[]).order(), BigUint::from(720_u32));
+
+    // only the rotations by 0 and 2 and two reflections fix {0, 2} in D4
+    let mut g = Group::dihedral(4);
+    assert_eq!(g.setwise_stabilizer(&[0, 2]).order(), BigUint::from(4_u32));
+    let mut g = Group::cyclic_group(4);
+    assert_eq!(g.setwise_stabilizer(&[0, 1]).order(), BigUint::from(1_u32));
+}
+
+#[test]
+fn test_centralizer() {
+    let mut g = Group::symmetric(5);
+    let c = Perm::new_cyc(5, vec![vec![0, 1, 2, 3, 4]]);
+    let mut h = g.centralizer(&c);
+    assert_eq!(h.order(), BigUint::from(5_u32));
+    assert!(h.elements().all(|x| (0..5).any(|k| x == c.pow(k))));
+
+    // a transposition in S5 is centralized by itself times S3
+    let t = Perm::new_cyc(5, vec![vec![1, 3]]);
+    assert_eq!(g.centralizer(&t).order(), BigUint::from(12_u32));
+    assert_eq!(g.centralizer(&Perm::id(5)).order(), BigUint::from(120_u32));
+}
 
 #[test]
-fn test_orb_cyclic() {
-    let g = Group::cyclic_group(5);
-    assert_eq!(g.orbit(0).len(), 5);
+#[should_panic]
+fn test_centralizer_not_member() {
+    Group::cyclic_group(5).centralizer(&Perm::new_cyc(5, vec![vec![0, 1]]));
 }
 
 #[test]
-fn test_orb_symmetric() {
-    let g = Group::symmetric(5);
-    assert_eq!(g.orbit(0).len(), 5);
-    assert_eq!(g.orbit(1).len(), 5);
-    assert_eq!(g.orbit(2).len(), 5);
-    assert_eq!(g.orbit(3).len(), 5);
-    assert_eq!(g.orbit(4).len(), 5);
+fn test_center() {
+    assert!(Group::symmetric(4).center().is_trivial());
+    assert!(Group::symmetric(5).center().is_trivial());
+    assert_eq!(Group::cyclic_group(6).center().order(), BigUint::from(6_u32));
+    // the rotation by half a turn is central in D4
+    let mut z = Group::dihedral(4).center();
+    assert_eq!(z.order(), BigUint::from(2_u32));
+    assert!(z.contains(&Perm::new_cyc(4, vec![vec![0, 2], vec![1, 3]])));
 }
 
 #[test]
-fn test_repr() {
-    // start with a group that is already an SGS; this is an SGS for S4
-    let g = Group::new(vec![
-        Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
-        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
+fn test_is_abelian() {
+    assert!(Group::cyclic_group(7).is_abelian());
+    assert!(!Group::symmetric(3).is_abelian());
+    assert!(Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1]]),
         Perm::new_cyc(4, vec![vec![2, 3]]),
-    ]);
+    ]).is_abelian());
+}
 
-    // generate the stabilizer chain
-    let s1 = StabView::new(&g, vec![], 0);
+#[test]
+fn test_derived_subgroup() {
+    let mut d = Group::symmetric(4).derived_subgroup();
+    assert_eq!(d.order(), BigUint::from(12_u32));
+    assert!(d.get_gens().iter().all(|p| p.is_even()));
+    assert_eq!(Group::alternating(4).derived_subgroup().order(), BigUint::from(4_u32));
+    assert!(Group::cyclic_group(6).derived_subgroup().is_trivial());
+}
 
-    // two perms are in the same coset if they both map the basis point
-    // to the same point, so let's verify that fact; both these cycles map
-    // the basis point 0 to 1 
-    let rep1 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]));
-    let rep2 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 1]]));
-    let rep3 = s1.repr(&Perm::new_cyc(4, vec![vec![0, 2]]));
-    assert_eq!(rep1, rep2);
-    assert_eq!(rep1.apply(0), 1);
-    assert_ne!(rep1, rep3);
+#[test]
+fn test_normal_closure() {
+    let mut g = Group::symmetric(4);
+    let t = Perm::new_cyc(4, vec![vec![0, 1]]);
+    assert_eq!(g.normal_closure(&[t]).order(), BigUint::from(24_u32));
+    // the double transpositions generate the Klein four-group, which is normal
+    let v = Perm::new_cyc(4, vec![vec![0, 1], vec![2, 3]]);
+    assert_eq!(g.normal_closure(&[v]).order(), BigUint::from(4_u32));
+    assert!(g.normal_closure(&[Perm::id(4)]).is_trivial());
+    // a reflection in D5 is conjugate to every reflection, which generate D5
+    let mut g = Group::dihedral(5);
+    let r = Perm::new(vec![0, 4, 3, 2, 1]);
+    assert_eq!(g.normal_closure(&[r]).order(), BigUint::from(10_u32));
 }
 
 #[test]
-fn test_strip() {
-    // start with a group that is already an SGS; this is an SGS for S4
-    let g = Group::new(vec![
+fn test_intersection() {
+    let mut h = Group::symmetric(5).stabilizer_of_points(&[0]);
+    let mut k = Group::symmetric(5).stabilizer_of_points(&[1]);
+    let mut i = h.intersection(&mut k);
+    assert_eq!(i.order(), BigUint::from(6_u32));
+    assert!(i.get_gens().iter().all(|p| p.apply(0) == 0 && p.apply(1) == 1));
+
+    let mut a = Group::alternating(4);
+    let mut d = Group::dihedral(4);
+    assert_eq!(a.intersection(&mut d).order(), BigUint::from(4_u32));
+}
+
+#[test]
+fn test_is_subgroup_of() {
+    let mut a = Group::alternating(4);
+    let mut s = Group::symmetric(4);
+    assert!(a.is_subgroup_of(&mut s));
+    assert!(!s.is_subgroup_of(&mut a));
+    assert!(!a.is_subgroup_of(&mut Group::symmetric(5)));
+}
+
+#[test]
+fn test_index_of() {
+    let mut g = Group::symmetric(5);
+    let mut h = g.stabilizer_of_points(&[2]);
+    assert_eq!(g.index_of(&mut h), BigUint::from(5_u32));
+    assert_eq!(g.index_of(&mut Group::alternating(5)), BigUint::from(2_u32));
+}
+
+#[test]
+#[should_panic]
+fn test_index_of_not_subgroup() {
+    Group::alternating(4).index_of(&mut Group::symmetric(4));
+}
+
+#[test]
+fn test_direct_product() {
+    let mut g = Group::cyclic_group(2).direct_product(&Group::cyclic_group(3));
+    assert_eq!(g.num_points(), 5);
+    assert_eq!(g.order(), BigUint::from(6_u32));
+    assert_eq!(g.all_orbits(), vec![vec![0, 1], vec![2, 3, 4]]);
+    let mut g = Group::symmetric(3).direct_product(&Group::dihedral(4));
+    assert_eq!(g.order(), BigUint::from(48_u32));
+}
+
+#[test]
+fn test_equals() {
+    let mut s = Group::symmetric(4);
+    let mut t = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1]]),
         Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
-        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
-        Perm::new_cyc(4, vec![vec![2, 3]]),
     ]);
+    assert!(s.equals(&mut t));
+    assert!(t.equals(&mut s));
+    assert!(!s.equals(&mut Group::alternating(4)));
+    assert!(!Group::alternating(4).equals(&mut s));
+    assert!(!s.equals(&mut Group::symmetric(5)));
+}
 
-    // generate the stabilizer chain
-    let chain = g.gen_stab_chain(&(0..3).collect());
-    // let s1 = StabView::new(&g, vec![], 0);
-    // let s2 = StabView::new(&g, vec![0], 1);
-    // let s3 = StabView::new(&g, vec![0, 1], 2);
-    // let s4 = StabView::new(&g, vec![0, 1, 2], 3);
-    // let chain = vec![s1, s2, s3, s4];
+#[cfg(feature = "rand")]
+#[test]
+fn test_reduce_generators() {
+    use rand::SeedableRng;
+    // bloat the transpositions with random elements, then reduce them
+    let mut g = Group::symmetric(10);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    for _ in 0..30 {
+        let p = g.random_element_seeded(&mut rng);
+        g.gens.push(p);
+    }
+    assert_eq!(g.num_gens(), 75);
+    g.reduce_generators();
+    assert!(g.num_gens() <= 9);
+    assert_eq!(g.order(), BigUint::from(3628800_u32));
 
-    // now see if we can properly strip elements of S4
-    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![0, 3]])), *g.id());
-    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![1, 2, 3]])), *g.id());
-    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![0, 2, 3]])), *g.id());
-    assert_eq!(g.strip(&chain, &Perm::new_cyc(4, vec![vec![3, 1, 2]])), *g.id());
+    let mut g = Group::dihedral(7);
+    g.schreier_sims();
+    g.reduce_generators();
+    assert!(g.num_gens() <= 6);
+    assert_eq!(g.order(), BigUint::from(14_u32));
+
+    let mut g = Group::trivial(3);
+    g.reduce_generators();
+    assert!(g.is_trivial());
 }
 
 #[test]
-fn test_size() {
-    // start with a group that is already an SGS; this is an SGS for S4
-    let g = Group::new(vec![
+fn test_verify_bsgs() {
+    let g = Group::symmetric(6);
+    assert!(g.verify_bsgs(&[0, 1, 2, 3, 4]));
+    assert!(g.verify_bsgs(&[5, 1, 3, 2, 0, 4]));
+    // the stabilizer of the first five points is trivial, but not of four
+    assert!(!g.verify_bsgs(&[0, 1, 2, 3]));
+    assert!(!g.verify_bsgs(&[0, 1, 1]));
+
+    let mut g = Group::new(vec![
+        Perm::new_cyc(6, vec![vec![0, 1]]),
+        Perm::new_cyc(6, vec![vec![0, 1, 2, 3, 4, 5]]),
+    ]);
+    let base : Vec<usize> = (0..6).collect();
+    assert!(!g.verify_bsgs(&base));
+    g.schreier_sims();
+    assert!(g.verify_bsgs(&base));
+
+    assert!(Group::trivial(3).verify_bsgs(&[]));
+    assert!(!Group::cyclic_group(3).verify_bsgs(&[]));
+}
+
+#[test]
+fn test_stabilizer_chain() {
+    let mut g = Group::new(vec![
+        Perm::new_cyc(4, vec![vec![0, 1]]),
         Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]),
-        Perm::new_cyc(4, vec![vec![1, 2, 3]]),
-        Perm::new_cyc(4, vec![vec![2, 3]]),
     ]);
-    assert_eq!(g.sz(), 4*3*2);
+    let chain = g.stabilizer_chain();
+    assert_eq!(chain.base(), &[0, 1, 2, 3]);
+    assert_eq!(chain.len(), 4);
+    assert_eq!(chain.orbit(0), vec![0, 1, 2, 3]);
+    assert_eq!(chain.orbit(1), vec![1, 2, 3]);
+    assert_eq!(chain.orbit(3), vec![3]);
+    for (p, u) in chain.orbit(1).into_iter().zip(chain.transversal(1)) {
+        assert_eq!(u.apply(1), p);
+        assert_eq!(u.apply(0), 0);
+        assert_eq!(chain.coset_rep(1, p), Some(u));
+    }
+    assert_eq!(chain.coset_rep(1, 0), None);
+
+    let mut g = Group::cyclic_group(4);
+    let chain = g.stabilizer_chain();
+    let (residue, level) = chain.sift(&Perm::new_cyc(4, vec![vec![0, 1, 3, 2]]));
+    assert_eq!(level, 1);
+    assert_eq!(residue.apply(0), 0);
+    assert_ne!(residue, Perm::id(4));
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_factor() {
+    let mut g = Group::new(vec![
+        Perm::new_cyc(5, vec![vec![0, 1]]),
+        Perm::new_cyc(5, vec![vec![0, 1, 2, 3, 4]]),
+    ]);
+    let gens = g.get_gens().clone();
+    let target = Perm::new_cyc(5, vec![vec![0, 3], vec![1, 4, 2]]);
+    let word = g.factor(&target).unwrap();
+    let product = word.iter().fold(Perm::id(5), |acc, &i| acc.compose(&gens[i]));
+    assert_eq!(product, target);
+    let word = g.factor(&Perm::id(5)).unwrap();
+    assert_eq!(word.iter().fold(Perm::id(5), |acc, &i| acc.compose(&gens[i])), Perm::id(5));
+
+    let mut g = Group::alternating(5);
+    assert_eq!(g.factor(&Perm::new_cyc(5, vec![vec![0, 1]])), None);
+    let gens = g.get_gens().clone();
+    for _ in 0..10 {
+        let target = g.random_element();
+        let word = g.factor(&target).unwrap();
+        let product = word.iter().fold(Perm::id(5), |acc, &i| acc.compose(&gens[i]));
+        assert_eq!(product, target);
+    }
+}
+
+#[test]
+fn test_factor_after_order() {
+    // order() adds strong generators, which the words must not refer to
+    let mut g = Group::symmetric_min(5);
+    let gens = g.get_gens().clone();
+    assert_eq!(g.order(), BigUint::from(120_u32));
+    assert!(g.num_gens() > gens.len());
+    for target in g.elements() {
+        let word = g.factor(&target).unwrap();
+        assert!(word.iter().all(|&i| i < gens.len()));
+        assert_eq!(word.iter().fold(Perm::id(5), |acc, &i| acc.compose(&gens[i])), target);
+    }
+}
+
+#[test]
+fn test_factor_cached() {
+    let mut g = Group::symmetric_min(4);
+    let target = Perm::new_cyc(4, vec![vec![0, 2], vec![1, 3]]);
+    let word = g.factor(&target).unwrap();
+    let chain = g.words.clone().unwrap();
+    assert_eq!(g.factor(&target).unwrap(), word);
+    assert!(Arc::ptr_eq(&chain, g.words.as_ref().unwrap()));
+    // a new base needs a new chain
+    g.change_base(&[3]);
+    let word = g.factor(&target).unwrap();
+    assert!(!Arc::ptr_eq(&chain, g.words.as_ref().unwrap()));
+    let gens = g.original_gens().clone();
+    assert_eq!(word.iter().fold(Perm::id(4), |acc, &i| acc.compose(&gens[i])), target);
+}
+
+#[test]
+fn test_factor_after_reduce() {
+    // the words stay in the original generators after reducing
+    let mut g = Group::symmetric(4);
+    let gens = g.get_gens().clone();
+    g.reduce_generators();
+    assert!(g.num_gens() < gens.len());
+    assert_eq!(g.original_gens(), &gens);
+    let target = Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]);
+    let word = g.factor(&target).unwrap();
+    assert_eq!(word.iter().fold(Perm::id(4), |acc, &i| acc.compose(&gens[i])), target);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_grng_cached_inverses() {
+    use rand::SeedableRng;
+    // product replacement as it was before inverses were cached
+    fn step_uncached(gens: &mut [Perm], rng: &mut impl Rng) {
+        let i = rng.gen_range(0..(gens.len()));
+        let j = {
+          let v = rng.gen_range(0..(gens.len() - 1));
+          if v >= i { v + 1 } else { v }
+        };
+        let choose_r : bool = rng.gen_bool(0.5);
+        let chooseplus : bool = rng.gen_bool(0.5);
+        gens[i] = match (choose_r, chooseplus) {
+            (true, true) => gens[i].compose(&gens[j]),
+            (true, false) => gens[i].compose(&gens[j].inv()),
+            (false, true) => gens[j].compose(&gens[i]),
+            (false, false) => gens[j].inv().compose(&gens[i])
+        };
+    }
+
+    let g = Group::rubiks();
+    let mut grng = GroupRNG::with_rng(&g, rand::rngs::StdRng::seed_from_u64(7));
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    let mut gens = g.gens.clone();
+    for _ in 0..3 {
+        for _ in 0..(4*g.n) {
+            step_uncached(&mut gens, &mut rng);
+        }
+        let expected = gens[rng.gen_range(0..(gens.len()))].clone();
+        assert_eq!(grng.draw(), expected);
+    }
+    for (x, xi) in grng.gens.iter().zip(grng.invs.iter()) {
+        assert_eq!(&x.inv(), xi);
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_orbit_par() {
+    let g = Group::symmetric(12);
+    for p in 0..12 {
+        assert_eq!(g.orbit_par(p), g.orbit(p));
+    }
+    let g = Group::new(vec![Perm::new_cyc(6, vec![vec![0, 1], vec![2, 3, 4]])]);
+    assert_eq!(g.orbit_par(2), g.orbit(2));
+    assert_eq!(g.orbit_par(5), g.orbit(5));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_roundtrip() {
+    let g = Group::rubiks();
+    let json = serde_json::to_string(&g).unwrap();
+    let h : Group = serde_json::from_str(&json).unwrap();
+    assert_eq!(h.num_points(), g.num_points());
+    assert_eq!(h.get_gens(), g.get_gens());
+
+    // generators are validated on the way in
+    assert!(serde_json::from_str::<Group>(r#"{"degree":3,"gens":[[1,0]]}"#).is_err());
+    assert!(serde_json::from_str::<Group>(r#"{"degree":2,"gens":[[1,1]]}"#).is_err());
+    assert!(serde_json::from_str::<Group>(r#"{"degree":2,"gens":[[1,0]],"base":[0,0]}"#).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_keeps_sgs() {
+    let mut g = Group::rubiks();
+    let base : Vec<usize> = (1..49).rev().collect();
+    g.schreier_sims_with_base(&base);
+    let json = serde_json::to_string(&g).unwrap();
+    let mut h : Group = serde_json::from_str(&json).unwrap();
+    // the strong generating set is verified rather than recomputed
+    assert!(h.is_sgs);
+    assert_eq!(h.base, g.base);
+    assert_eq!(h.get_gens(), g.get_gens());
+    assert_eq!(h.order(), g.order());
+
+    // generators that are not a strong generating set for the base
+    let json = r#"{"degree":3,"gens":[[1,2,0],[1,0,2]],"base":[0]}"#;
+    let mut h : Group = serde_json::from_str(json).unwrap();
+    assert!(!h.is_sgs);
+    assert_eq!(h.base, vec![0, 1, 2]);
+    assert_eq!(h.order(), BigUint::from(6_u32));
+    // a group whose generating set is not known to be strong has no base
+    assert!(!serde_json::to_string(&Group::symmetric(3)).unwrap().contains("base"));
+}
+
+#[test]
+fn test_gap_roundtrip() {
+    let g = Group::symmetric(4);
+    let h = Group::from_gap(4, &g.to_gap()).unwrap();
+    assert_eq!(h.get_gens(), g.get_gens());
+
+    let g = Group::from_gap(3, "Group((1,2,3),(1,2))").unwrap();
+    assert_eq!(g.get_gens(), &vec![Perm::new_cyc(3, vec![vec![0, 1, 2]]), Perm::new_cyc(3, vec![vec![0, 1]])]);
+    assert_eq!(g.to_gap(), "Group((1,2,3),(1,2))");
+    assert_eq!(Group::from_gap(3, "Group(())").unwrap().to_gap(), "Group(())");
+
+    assert!(matches!(Group::from_gap(3, "Gruop((1,2))"), Err(ParseError::Syntax(_))));
+    assert!(matches!(Group::from_gap(3, "Group((1,2),(0,1))"), Err(ParseError::Generator { index: 1, .. })));
+    assert_eq!(Group::from_gap(3, "Group((1,4))").unwrap_err(),
+               ParseError::Generator { index: 0, error: PermError::OutOfRange { image: 4, degree: 3 } });
+}
+
+#[test]
+fn test_gap_rubiks() {
+    let s = "Group(
+        ( 1, 3, 8, 6)( 2, 5, 7, 4)( 9,33,25,17)(10,34,26,18)(11,35,27,19),
+        ( 9,11,16,14)(10,13,15,12)( 1,17,41,40)( 4,20,44,37)( 6,22,46,35),
+        (17,19,24,22)(18,21,23,20)( 6,25,43,16)( 7,28,42,13)( 8,30,41,11),
+        (25,27,32,30)(26,29,31,28)( 3,38,43,19)( 5,36,45,21)( 8,33,48,24),
+        (33,35,40,38)(34,37,39,36)( 3, 9,46,32)( 2,12,47,29)( 1,14,48,27),
+        (41,43,48,46)(42,45,47,44)(14,22,30,38)(15,23,31,39)(16,24,32,40) )";
+    let g = Group::from_gap(48, s).unwrap();
+    let r = Group::rubiks();
+    assert_eq!(g.num_gens(), r.num_gens());
+    // the literal rubiks group has an unused point 0 in place of the shift
+    for (x, y) in g.get_gens().iter().zip(r.get_gens().iter()) {
+        for p in 0..48 {
+            assert_eq!(x.apply(p) + 1, y.apply(p + 1));
+        }
+    }
+}
+
+#[test]
+fn test_from_generators_checked() {
+    let g = Group::from_generators_checked(vec![Perm::new_cyc(4, vec![vec![0, 1, 2, 3]])]).unwrap();
+    assert_eq!(g.num_points(), 4);
+    assert_eq!(Group::from_generators_checked(vec![]).unwrap_err(), GroupError::NoGenerators);
+    let gens = vec![Perm::id(4), Perm::id(4), Perm::id(3)];
+    assert_eq!(Group::from_generators_checked(gens).unwrap_err(),
+               GroupError::DegreeMismatch { index: 2, expected: 4, found: 3 });
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_uniform_random_element() {
+    use rand::SeedableRng;
+    let mut g = Group::symmetric(4);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    let samples = 24 * 500;
+    let mut counts : HashMap<Perm, usize> = HashMap::new();
+    for _ in 0..samples {
+        *counts.entry(g.uniform_random_element(&mut rng)).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 24);
+    // chi-squared statistic against the flat distribution; 49.7 is the
+    // 0.999 quantile with 23 degrees of freedom
+    let expected = (samples / 24) as f64;
+    let chi2 : f64 = counts.values().map(|&c| (c as f64 - expected).powi(2) / expected).sum();
+    assert!(chi2 < 49.7, "chi-squared statistic {}", chi2);
+}
+
+#[test]
+fn test_num_orbits_on_subsets() {
+    // the 2-subsets of a square are its 4 edges and 2 diagonals
+    let mut g = Group::cyclic_group(4);
+    assert_eq!(g.num_orbits_on_subsets(2), BigUint::from(2_u32));
+    assert_eq!(g.num_orbits_on_subsets(0), BigUint::from(1_u32));
+    assert_eq!(g.num_orbits_on_subsets(1), BigUint::from(1_u32));
+    assert_eq!(g.num_orbits_on_subsets(5), BigUint::from(0_u32));
+    let mut g = Group::symmetric(6);
+    for k in 0..=6 {
+        assert_eq!(g.num_orbits_on_subsets(k), BigUint::from(1_u32));
+    }
+    let mut g = Group::trivial(5);
+    assert_eq!(g.num_orbits_on_subsets(2), BigUint::from(10_u32));
+}
+
+#[test]
+fn test_cycle_index() {
+    let q = |a: i64, b: i64| BigRational::new(BigInt::from(a), BigInt::from(b));
+    // Z(C_3) = (a_1^3 + 2 a_3) / 3
+    let mut g = Group::cyclic_group(3);
+    let z = g.cycle_index();
+    assert_eq!(z.terms().len(), 2);
+    assert_eq!(z.coefficient(&[3]), q(1, 3));
+    assert_eq!(z.coefficient(&[0, 0, 1]), q(2, 3));
+    assert_eq!(z.coefficient(&[1, 1]), q(0, 1));
+    // necklaces of 3 beads in 2 colors
+    let two = BigUint::from(2_u32);
+    assert_eq!(z.substitute(&[two.clone(), two.clone(), two]), q(4, 1));
+
+    // Z(S_3) = (a_1^3 + 3 a_1 a_2 + 2 a_3) / 6
+    let z = Group::symmetric(3).cycle_index();
+    assert_eq!(z.coefficient(&[1, 1, 0]), q(1, 2));
+    let sum : BigRational = z.terms().values().cloned().sum();
+    assert_eq!(sum, q(1, 1));
+}
+
+#[test]
+fn test_cayley_table() {
+    let mut g = Group::cyclic_group(3);
+    let (elems, table) = g.cayley_table();
+    assert_eq!(elems.len(), 3);
+    let e = elems.iter().position(|x| x == g.id()).unwrap();
+    let r = elems.iter().position(|x| x.apply(0) == 1).unwrap();
+    let r2 = elems.iter().position(|x| x.apply(0) == 2).unwrap();
+    // the table of Z/3 with rows and columns ordered e, r, r^2
+    let expected = [[e, r, r2], [r, r2, e], [r2, e, r]];
+    for (i, &x) in [e, r, r2].iter().enumerate() {
+        for (j, &y) in [e, r, r2].iter().enumerate() {
+            assert_eq!(table[x][y], expected[i][j]);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "too large")]
+fn test_cayley_table_limit() {
+    Group::symmetric(5).cayley_table_with_limit(100);
+}
+
+#[test]
+fn test_conjugacy_classes() {
+    let mut g = Group::symmetric(4);
+    let classes = g.conjugacy_classes();
+    let mut sizes : Vec<usize> = classes.iter().map(|c| c.len()).collect();
+    sizes.sort_unstable();
+    // identity, double transpositions, transpositions, 4-cycles, 3-cycles
+    assert_eq!(sizes, vec![1, 3, 6, 6, 8]);
+    // each class has a single cycle type
+    for c in classes.iter() {
+        assert!(c.iter().all(|x| x.cycle_type() == c[0].cycle_type()));
+    }
+    assert_eq!(Group::cyclic_group(5).conjugacy_classes().len(), 5);
+}
+
+#[test]
+fn test_minimal_block_system() {
+    // the diagonals of a square form blocks
+    let g = Group::dihedral(4);
+    assert_eq!(g.minimal_block_system(), Some(vec![vec![0, 2], vec![1, 3]]));
+    assert_eq!(Group::symmetric(5).minimal_block_system(), None);
+    assert_eq!(Group::cyclic_group(5).minimal_block_system(), None);
+    // the rotations of a hexagon preserve pairs of opposite vertices and
+    // triangles; the pairs are smaller
+    let blocks = Group::cyclic_group(6).minimal_block_system().unwrap();
+    assert_eq!(blocks, vec![vec![0, 3], vec![1, 4], vec![2, 5]]);
+    // block systems are only defined for transitive groups
+    assert_eq!(Group::trivial(3).minimal_block_system(), None);
+    let g = Group::new(vec![Perm::new_cyc(4, vec![vec![0, 1]])]);
+    assert_eq!(g.minimal_block_system(), None);
+}
+
+#[test]
+fn test_is_primitive() {
+    assert!(Group::symmetric(5).is_primitive());
+    assert!(Group::cyclic_group(7).is_primitive());
+    assert!(!Group::dihedral(6).is_primitive());
+    // intransitive groups are never primitive
+    assert!(!Group::new(vec![Perm::new_cyc(4, vec![vec![0, 1]])]).is_primitive());
+}
+
+#[test]
+fn test_transitivity_degree() {
+    assert_eq!(Group::symmetric(5).transitivity_degree(), 5);
+    assert_eq!(Group::alternating(5).transitivity_degree(), 3);
+    assert_eq!(Group::dihedral(5).transitivity_degree(), 1);
+    assert_eq!(Group::new(vec![Perm::new_cyc(4, vec![vec![0, 1]])]).transitivity_degree(), 0);
+}
+
+#[test]
+fn test_orbit_of_tuple() {
+    let g = Group::symmetric(4);
+    assert_eq!(g.orbit_of_tuple(&[0, 1]).len(), 12);
+    assert_eq!(g.orbit_of_tuple(&[2, 2]).len(), 4);
+    let g = Group::cyclic_group(5);
+    let orbit = g.orbit_of_tuple(&[0, 1, 3]);
+    assert_eq!(orbit.len(), 5);
+    assert!(orbit.contains(&vec![4, 0, 2]));
+}
+
+#[test]
+fn test_base_orbit_sizes() {
+    let mut g = Group::symmetric(5);
+    assert_eq!(g.base_orbit_sizes(&[0, 1, 2, 3, 4]), vec![5, 4, 3, 2, 1]);
+    let mut g = Group::dihedral(6);
+    // the reflection fixing 3 also fixes the opposite vertex 0
+    let sizes = g.base_orbit_sizes(&[3]);
+    assert_eq!(sizes[..3], [6, 1, 2]);
+    assert_eq!(sizes.iter().product::<usize>(), 12);
+}
+
+#[test]
+fn test_symmetric_min() {
+    let mut g = Group::symmetric_min(6);
+    assert_eq!(g.num_gens(), 2);
+    assert_eq!(Group::symmetric(6).num_gens(), 15);
+    assert_eq!(g.order(), BigUint::from(720_u32));
+    assert_eq!(Group::symmetric_min(2).order(), BigUint::from(2_u32));
+    assert_eq!(Group::symmetric_min(1).order(), BigUint::from(1_u32));
+}
+
+#[test]
+fn test_from_cycles() {
+    let mut g = Group::from_cycles(3, vec![vec![vec![0, 1]], vec![vec![0, 1, 2]]]);
+    assert_eq!(g.num_gens(), 2);
+    assert_eq!(g.get_gens()[1], Perm::new(vec![1, 2, 0]));
+    assert_eq!(g.order(), BigUint::from(6_u32));
+}
+
+#[test]
+fn test_induced_on_orbit() {
+    // S_3 on {0, 1, 2} times C_2 on {3, 4}
+    let g = Group::symmetric(3).direct_product(&Group::cyclic_group(2));
+    let mut h = g.induced_on_orbit(1);
+    assert_eq!(h.num_points(), 3);
+    assert_eq!(h.order(), BigUint::from(6_u32));
+    let mut h = g.induced_on_orbit(4);
+    assert_eq!(h.num_points(), 2);
+    assert_eq!(h.order(), BigUint::from(2_u32));
+    assert_eq!(g.induced_on_orbit(3).get_gens()[3], Perm::new(vec![1, 0]));
+}
+
+#[test]
+fn test_wreath_product() {
+    let mut g = Group::wreath_product(&Group::cyclic_group(2), &Group::symmetric(2));
+    assert_eq!(g.num_points(), 4);
+    assert_eq!(g.order(), BigUint::from(8_u32));
+    let mut g = Group::wreath_product(&Group::symmetric(3), &Group::cyclic_group(2));
+    assert_eq!(g.order(), BigUint::from(72_u32));
+    assert_eq!(g.minimal_block_system(), Some(vec![vec![0, 1, 2], vec![3, 4, 5]]));
+    // the blocks are not moved by a trivial top group
+    let mut g = Group::wreath_product(&Group::cyclic_group(2), &Group::trivial(3));
+    assert_eq!(g.order(), BigUint::from(8_u32));
+}
+
+#[test]
+fn test_orbit_representatives() {
+    let g = Group::new(vec![Perm::new_cyc(5, vec![vec![0, 1], vec![2, 3]])]);
+    assert_eq!(g.orbit_representatives(), vec![0, 2, 4]);
+    assert_eq!(Group::symmetric(4).orbit_representatives(), vec![0]);
+    assert_eq!(Group::trivial(3).orbit_representatives(), vec![0, 1, 2]);
+}
+
+#[test]
+fn test_exponent() {
+    assert_eq!(Group::symmetric(4).exponent(), 12);
+    assert_eq!(Group::cyclic_group(7).exponent(), 7);
+    assert_eq!(Group::dihedral(4).exponent(), 4);
+    assert_eq!(Group::trivial(3).exponent(), 1);
+}
+
+#[test]
+fn test_max_order_element() {
+    // Landau's function g(7) = 12, from a 3-cycle and a disjoint 4-cycle
+    let (p, o) = Group::symmetric(7).max_order_element();
+    assert_eq!(o, 12);
+    assert_eq!(p.order(), 12);
+    assert_eq!(Group::trivial(2).max_order_element(), (Perm::id(2), 1));
+    assert_eq!(Group::dihedral(5).max_order_element().1, 5);
+}
+
+#[test]
+fn test_subgroup() {
+    let g = Group::symmetric(5);
+    let mut h = g.subgroup(vec![Perm::new_cyc(5, vec![vec![0, 1, 2, 3, 4]])]);
+    assert_eq!(h.order(), BigUint::from(5_u32));
+    let mut h = g.subgroup(vec![]);
+    assert_eq!(h.num_points(), 5);
+    assert!(h.is_trivial());
+}
+
+#[test]
+#[should_panic(expected = "wrong degree")]
+fn test_subgroup_degree() {
+    Group::symmetric(5).subgroup(vec![Perm::id(4)]);
+}
+
+#[test]
+fn test_conjugate_by() {
+    let mut g = Group::symmetric(4);
+    let x = Perm::new_cyc(4, vec![vec![0, 2, 3]]);
+    assert!(g.conjugate_by(&x).equals(&mut g));
+
+    let mut h = g.stabilizer_of_points(&[0]);
+    let mut k = h.conjugate_by(&Perm::new_cyc(4, vec![vec![0, 1]]));
+    assert_eq!(k.order(), h.order());
+    assert!(k.get_gens().iter().all(|p| p.apply(1) == 1));
+    assert!(!k.equals(&mut h));
+
+    // S_1 has no generators
+    let mut k = Group::symmetric(1).conjugate_by(&Perm::id(1));
+    assert_eq!(k.num_points(), 1);
+    assert!(k.is_trivial());
+}
+
+#[test]
+fn test_right_cosets_of() {
+    let mut g = Group::symmetric(4);
+    let mut h = g.stabilizer_of_points(&[0]);
+    let reps = g.right_cosets_of(&mut h);
+    assert_eq!(reps.len(), 4);
+    assert_eq!(BigUint::from(reps.len()), g.index_of(&mut h));
+    // the cosets H g are distinguished by where g^-1 sends 0
+    let mut images : Vec<usize> = reps.iter().map(|r| r.inv().apply(0)).collect();
+    images.sort_unstable();
+    assert_eq!(images, vec![0, 1, 2, 3]);
+
+    let mut t = Group::trivial(4);
+    assert_eq!(g.right_cosets_of(&mut t).len(), 24);
+}
+
+#[test]
+fn test_is_regular() {
+    assert!(Group::cyclic_group(5).is_regular());
+    assert!(!Group::symmetric(5).is_regular());
+    // the Klein four-group acting on itself
+    assert!(Group::from_cycles(4, vec![vec![vec![0, 1], vec![2, 3]], vec![vec![0, 2], vec![1, 3]]]).is_regular());
+    // order 2 on 4 points, but not transitive
+    assert!(!Group::from_cycles(4, vec![vec![vec![0, 1]]]).is_regular());
+}
+
+#[test]
+fn test_change_base() {
+    let mut g = Group::symmetric(6);
+    g.schreier_sims();
+    g.change_base(&[5, 3]);
+    assert_eq!(g.stabilizer_chain().base()[..2], [5, 3]);
+    assert!(g.verify_bsgs(&g.base));
+    assert_eq!(g.order(), BigUint::from(720_u32));
+    assert_eq!(g.stabilizer_chain().orbit(0), vec![0, 1, 2, 3, 4, 5]);
+
+    // 2 is not in the orbit of 0, so it has to be moved up the base
+    let mut g = Group::from_cycles(5, vec![vec![vec![0, 1]], vec![vec![2, 3, 4]]]);
+    g.schreier_sims();
+    g.change_base(&[2, 0]);
+    assert_eq!(g.stabilizer_chain().base()[..2], [2, 0]);
+    assert!(g.verify_bsgs(&g.base));
+    assert_eq!(g.order(), BigUint::from(6_u32));
+    assert_eq!(g.stabilizer_chain().orbit(0), vec![2, 3, 4]);
+}
+
+#[test]
+fn test_schreier_tree_depths() {
+    let mut g = Group::cyclic_group(6);
+    let chain = g.stabilizer_chain();
+    assert_eq!(chain.schreier_tree_depths()[0], 5);
+    assert!(chain.schreier_tree_depths()[1..].iter().all(|&d| d == 0));
+    // every point is one transposition away from 0
+    let mut g = Group::symmetric(5);
+    assert_eq!(g.stabilizer_chain().schreier_tree_depths()[0], 1);
+}
+
+#[test]
+fn test_orbit_of_set() {
+    let g = Group::cyclic_group(4);
+    let orbit = g.orbit_of_set(&[0, 1]);
+    assert_eq!(orbit.len(), 4);
+    assert!(orbit.contains(&[3, 0].iter().copied().collect()));
+    // the diagonals of the square
+    assert_eq!(g.orbit_of_set(&[2, 0]).len(), 2);
+    assert_eq!(Group::symmetric(5).orbit_of_set(&[1, 2, 4]).len(), 10);
+}
+
+// the permutation and deterministic group core does not need rand; check it
+// with `cargo test --no-default-features`
+#[test]
+fn test_core_without_rand() {
+    let mut g = Group::from_cycles(5, vec![vec![vec![0, 1, 2, 3, 4]], vec![vec![0, 1]]]);
+    g.schreier_sims();
+    assert_eq!(g.order(), BigUint::from(120_u32));
+    assert!(g.contains(&Perm::new_cyc(5, vec![vec![1, 3]])));
+    g.change_base(&[4, 2]);
+    assert_eq!(g.order(), BigUint::from(120_u32));
+}
+
+#[test]
+fn test_is_symmetric_alternating() {
+    assert!(Group::symmetric(6).is_symmetric());
+    assert!(!Group::symmetric(6).is_alternating());
+    assert!(Group::alternating(6).is_alternating());
+    assert!(!Group::alternating(6).is_symmetric());
+    assert!(Group::symmetric_min(5).is_symmetric());
+    assert!(!Group::dihedral(6).is_symmetric());
+    assert!(!Group::cyclic_group(3).is_symmetric());
+    // C_3 is A_3
+    assert!(Group::cyclic_group(3).is_alternating());
+    // S_3 acting on {0, 1, 2} of 4 points has order 3! but is not S_4
+    assert!(!Group::symmetric(3).direct_product(&Group::trivial(1)).is_symmetric());
+}
+
+#[test]
+fn test_order_statistics() {
+    let stats = Group::symmetric(4).order_statistics();
+    let expected : BTreeMap<usize, BigUint> = vec![(1, 1_u32), (2, 9), (3, 8), (4, 6)].into_iter()
+        .map(|(o, c)| (o, BigUint::from(c)))
+        .collect();
+    assert_eq!(stats, expected);
+    assert_eq!(Group::cyclic_group(6).order_statistics().get(&6), Some(&BigUint::from(2_u32)));
+}
+
+#[test]
+fn test_subgroup_search() {
+    let mut g = Group::symmetric(4);
+    let in_set = |p: usize| p < 2;
+    let mut h = g.subgroup_search(|x| (0..4).all(|p| in_set(p) == in_set(x.apply(p))));
+    assert_eq!(h.order(), BigUint::from(4_u32));
+    assert!(h.equals(&mut g.setwise_stabilizer(&[0, 1])));
+    // the even permutations
+    let mut h = Group::symmetric(5).subgroup_search(|x| x.is_even());
+    assert!(h.equals(&mut Group::alternating(5)));
+    assert!(Group::symmetric(4).subgroup_search(|x| x.is_identity()).is_trivial());
+}
+
+#[test]
+fn test_partition_refine() {
+    // splitting off a point of a cycle of g splits off the rest of the cycle
+    let g = Perm::new_cyc(6, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+    let refiners = [Refiner::Centralizes(&g)];
+    let mut p = Partition::new(6);
+    p.refine(&refiners, true);
+    assert_eq!(p.shape(), vec![6]);
+    p.individualize(4);
+    p.refine(&refiners, false);
+    assert_eq!(p.cells, vec![vec![4], vec![3], vec![5], vec![0, 1, 2]]);
+    assert!((0..6).all(|q| p.cells[p.cell_of[q]].contains(&q)));
+    // labels split the cells in increasing order of label
+    let mut p = Partition::new(4);
+    p.refine(&[Refiner::Labels(vec![1, 0, 1, 0])], true);
+    assert_eq!(p.cells, vec![vec![1, 3], vec![0, 2]]);
+}
+
+#[test]
+fn test_subgroup_search_refined_prunes() {
+    use std::cell::Cell;
+    // the refined search reaches few elements the property rules out
+    let mut g = Group::symmetric(8);
+    let set = [0, 1, 2];
+    let in_set = |x: &Perm| set.iter().all(|&p| x.apply(p) < 3);
+    let plain = Cell::new(0);
+    let mut h = g.subgroup_search(|x| { plain.set(plain.get() + 1); in_set(x) });
+    let refined = Cell::new(0);
+    let labels = (0..8).map(|p| (p < 3) as usize).collect();
+    let mut k = g.subgroup_search_refined(&[Refiner::Labels(labels)], |x| { refined.set(refined.get() + 1); in_set(x) });
+    assert_eq!(h.order(), BigUint::from(720_u32));
+    assert!(h.equals(&mut k));
+    assert!(refined.get() * 10 < plain.get(), "{} vs {}", refined.get(), plain.get());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_subgroup_search_refined_random() {
+    use rand::SeedableRng;
+    // compare with enumerating the group, on random subgroups of S_6
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    let s6 = Group::symmetric(6);
+    for _ in 0..20 {
+        let mut g = Group::new(vec![s6.random_element_seeded(&mut rng), s6.random_element_seeded(&mut rng)]);
+        let mut h = Group::new(vec![s6.random_element_seeded(&mut rng)]);
+        let x = g.random_element_seeded(&mut rng);
+        let elements : Vec<Perm> = g.elements().collect();
+        let count = |f: &dyn Fn(&Perm) -> bool| BigUint::from(elements.iter().filter(|y| f(y)).count());
+        assert_eq!(g.centralizer(&x).order(), count(&|y| y.compose(&x) == x.compose(y)));
+        assert_eq!(g.setwise_stabilizer(&[0, 3]).order(), count(&|y| [0, 3].iter().all(|&p| [0, 3].contains(&y.apply(p)))));
+        assert_eq!(g.intersection(&mut h).order(), count(&|y| h.in_group(y)));
+        let gens = g.get_gens().clone();
+        assert_eq!(g.center().order(), count(&|y| gens.iter().all(|z| y.compose(z) == z.compose(y))));
+        let mut k = g.subgroup(vec![x.clone()]);
+        k.order();
+        assert_eq!(g.normalizer(&mut k).order(), count(&|y| k.get_gens().iter().all(|z| k.in_group(&z.conjugate(y)))));
+    }
+}
+
+#[test]
+fn test_subgroup_search_matches_enumeration() {
+    // the pruned search must find exactly the elements with the property
+    let mut g = Group::symmetric(6);
+    let pair = |p: usize| p / 2;
+    let property = |x: &Perm| (0..6).all(|p| pair(x.apply(p)) == pair(x.apply(p ^ 1)));
+    let count = g.elements().filter(|x| property(x)).count();
+    assert_eq!(count, 48);
+    let mut h = g.subgroup_search(property);
+    assert_eq!(h.order(), BigUint::from(count));
+    // a point stabilizer with a non-default base
+    let mut g = Group::dihedral(6);
+    g.change_base(&[3, 1]);
+    assert_eq!(g.subgroup_search(|x| x.apply(2) == 2).order(), BigUint::from(2_u32));
+    // the normalizer of a 6-cycle in S_6 is the holomorph of C_6
+    let mut c = Group::cyclic_group(6);
+    assert_eq!(Group::symmetric(6).normalizer(&mut c).order(), BigUint::from(12_u32));
+}
+
+#[test]
+fn test_normalizer() {
+    let mut g = Group::symmetric(4);
+    // the dihedral group of the square is a Sylow 2-subgroup of S_4 and is
+    // its own normalizer
+    let mut p = Group::dihedral(4);
+    let mut n = g.normalizer(&mut p);
+    assert_eq!(n.order(), BigUint::from(8_u32));
+    assert!(n.equals(&mut p));
+    let mut n = g.normalizer(&mut Group::alternating(4));
+    assert_eq!(n.order(), BigUint::from(24_u32));
+    let mut c = Group::from_cycles(4, vec![vec![vec![0, 1, 2]]]);
+    assert_eq!(g.normalizer(&mut c).order(), BigUint::from(6_u32));
+}
+
+#[test]
+#[should_panic(expected = "not a subgroup")]
+fn test_normalizer_not_subgroup() {
+    Group::alternating(4).normalizer(&mut Group::dihedral(4));
 }
 
+#[cfg(feature = "rand")]
 #[test]
 fn test_schreier() {
     let mut g = Group::symmetric(12);
@@ -0,0 +1,8 @@
+pub mod perm;
+pub mod group;
+pub mod naive;
+#[cfg(feature = "rand")]
+pub mod rubiks;
+
+#[cfg(feature = "rand")]
+extern crate rand;
@@ -1,18 +1,10 @@
-pub mod perm;
-pub mod group;
-pub mod naive;
-
-extern crate rand;
-
-use perm::*;
-use group::*;
+use rs_perm::group::*;
+use num_bigint::BigUint;
 
 
 fn main() {
     let mut g = Group::rubiks();
-    g.random_schreier_sims();
-    let sz = 3;
-    // let sz : usize = (2 as usize).pow(27) * (3 as usize).pow(14) * (5 as usize).pow(3) * (7 as usize).pow(2) * 11;
-    assert_eq!(g.sz(), sz);
+    let sz = BigUint::from(2_u32).pow(27) * BigUint::from(3_u32).pow(14)
+        * BigUint::from(5_u32).pow(3) * BigUint::from(7_u32).pow(2) * BigUint::from(11_u32);
+    assert_eq!(g.order(), sz);
 }
-
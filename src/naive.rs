@@ -0,0 +1,63 @@
+use super::group::*;
+use super::perm::*;
+
+use std::collections::HashSet;
+
+// the Schreier generators u_{s(q)}^-1 s u_q for every generator s and every
+// q in the orbit of p, where u_q is a coset representative mapping p to q;
+// these include many identities and repeats
+fn schreier_generators(g: &Group, p: usize) -> Vec<Perm> {
+    let (_, reps) = g.orbit_schreier(p);
+    let mut gens : Vec<Perm> = Vec::new();
+    for u in reps.iter().flatten() {
+        for s in g.get_gens().iter() {
+            let su = s.compose(u);
+            let v = reps[su.apply(p)].as_ref().unwrap();
+            gens.push(v.inv().compose(&su));
+        }
+    }
+    gens
+}
+
+/// The stabilizer of the point p, generated by the Schreier generators with
+/// identities and repeats dropped; if nothing is left the stabilizer is
+/// trivial
+pub fn stab_group(g: &Group, p: usize) -> Group {
+    let mut seen : HashSet<Perm> = HashSet::new();
+    let gens : Vec<Perm> = schreier_generators(g, p).into_iter()
+        .filter(|x| !x.is_identity() && seen.insert(x.clone()))
+        .collect();
+    if gens.is_empty() {
+        return Group::trivial(g.num_points());
+    }
+    Group::new(gens)
+}
+
+#[test]
+fn stab_group_fixed_point_test() {
+    // 4 is fixed, so its stabilizer is the whole group
+    let g = Group::new(vec![Perm::new_cyc(5, vec![vec![0, 1, 2]])]);
+    let mut h = stab_group(&g, 4);
+    assert_eq!(h.order(), num_bigint::BigUint::from(3_u32));
+    // the cyclic group acts regularly, so stabilizers only have identities
+    let mut h = stab_group(&Group::cyclic_group(4), 0);
+    assert_eq!(h.num_points(), 4);
+    assert!(h.is_trivial());
+}
+
+#[test]
+fn stab_group_symmetric_test() {
+    let mut h = stab_group(&Group::symmetric(5), 0);
+    assert_eq!(h.order(), num_bigint::BigUint::from(24_u32));
+    assert!(h.get_gens().iter().all(|x| x.apply(0) == 0));
+}
+
+#[test]
+fn stab_group_dedup_test() {
+    let g = Group::symmetric(6);
+    let mut h = stab_group(&stab_group(&g, 0), 1);
+    let mut raw = Group::new(schreier_generators(&Group::new(schreier_generators(&g, 0)), 1));
+    assert!(h.num_gens() * 10 < raw.num_gens(), "{} vs {}", h.num_gens(), raw.num_gens());
+    assert_eq!(h.order(), raw.order());
+    assert_eq!(h.order(), num_bigint::BigUint::from(24_u32));
+}
@@ -1,16 +1,100 @@
+use num_bigint::BigUint;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Mul;
+
+/// Errors arising from constructing an invalid permutation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermError {
+    /// `image` is not a point of a permutation of degree `degree`
+    OutOfRange { image: usize, degree: usize },
+    /// `image` is the image of more than one point
+    RepeatedImage { image: usize },
+    /// the input string is not valid cycle notation
+    Syntax(String),
+    /// `point` is in a set that should be preserved, but its image is not
+    NotPreserved { point: usize, image: usize },
+    /// `point` is given more than one image
+    RepeatedPoint { point: usize },
+    /// permutations of degrees `left` and `right` cannot be combined
+    DegreeMismatch { left: usize, right: usize },
+    /// row `row` of a matrix does not have a single 1 among 0s
+    BadMatrixRow { row: usize },
+}
+
+impl fmt::Display for PermError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PermError::OutOfRange { image, degree } =>
+                write!(f, "image {} is out of range for a permutation of degree {}", image, degree),
+            PermError::RepeatedImage { image } =>
+                write!(f, "image {} appears more than once", image),
+            PermError::Syntax(msg) => write!(f, "invalid cycle notation: {}", msg),
+            PermError::NotPreserved { point, image } =>
+                write!(f, "point {} is mapped to {} outside the set", point, image),
+            PermError::RepeatedPoint { point } =>
+                write!(f, "point {} is given more than one image", point),
+            PermError::DegreeMismatch { left, right } =>
+                write!(f, "cannot combine permutations of degrees {} and {}", left, right),
+            PermError::BadMatrixRow { row } =>
+                write!(f, "row {} is not a row of a permutation matrix", row),
+        }
+    }
+}
+
+impl std::error::Error for PermError {}
+
+/// check that `v` is a bijection on 0..v.len()
+fn validate(v: &[usize]) -> Result<(), PermError> {
+    let mut seen = vec![false; v.len()];
+    for &x in v.iter() {
+        if x >= v.len() {
+            return Err(PermError::OutOfRange { image: x, degree: v.len() });
+        }
+        if seen[x] {
+            return Err(PermError::RepeatedImage { image: x });
+        }
+        seen[x] = true;
+    }
+    Ok(())
+}
+
+/// A permutation of the points 0..n, stored as its images. The images are
+/// kept as u32 to halve the memory of a usize; the public API still takes
+/// and returns usize
 #[derive(Debug, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PermImages", into = "PermImages"))]
 pub struct Perm {
-    p: Vec<usize>
+    p: Vec<u32>
+}
+
+// narrow a vector of images to the stored representation
+fn images(v: Vec<usize>) -> Vec<u32> {
+    assert!(v.len() <= u32::MAX as usize, "degree {} is too large", v.len());
+    v.into_iter().map(|x| x as u32).collect()
 }
 
 impl Perm {
     pub fn apply(&self, point: usize) -> usize {
-        return self.p[point]
+        self.p[point] as usize
     }
 
-    /// Create a new permutation using matrix notation
+    /// Create a new permutation using matrix notation; the input is only
+    /// checked in debug builds, so use `try_new` for untrusted input
     pub fn new(v: Vec<usize>) -> Perm {
-        return Perm { p: v }
+        debug_assert!(validate(&v).is_ok(), "not a permutation: {:?}", v);
+        Perm { p: images(v) }
+    }
+
+    /// Create a new permutation using matrix notation, checking that `v` is
+    /// a bijection on 0..v.len()
+    pub fn try_new(v: Vec<usize>) -> Result<Perm, PermError> {
+        validate(&v)?;
+        Ok(Perm { p: images(v) })
     }
 
     /// Create a new permutation using cycle representation
@@ -22,33 +106,439 @@ impl Perm {
                 p[c[i]] = c[(i+1) % c.len()]
             }
         }
-        Perm {p}
+        Perm::new(p)
+    }
+
+    /// Parse a permutation of the given degree from cycle notation such as
+    /// `(1 3 8 6)(2 5 7 4)`; points within a cycle may be separated by
+    /// whitespace or commas
+    pub fn parse(degree: usize, s: &str) -> Result<Perm, PermError> {
+        Perm::parse_from(degree, s, 0)
+    }
+
+    /// Parse cycle notation whose points are numbered from `first`, e.g. 1
+    /// for GAP
+    pub(crate) fn parse_from(degree: usize, s: &str, first: usize) -> Result<Perm, PermError> {
+        let mut cycles : Vec<Vec<usize>> = Vec::new();
+        let mut seen = vec![false; degree];
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() { continue }
+            if c != '(' {
+                return Err(PermError::Syntax(format!("expected '(' but found '{}'", c)));
+            }
+            // read points until the closing paren
+            let mut cyc = Vec::new();
+            loop {
+                match chars.next() {
+                    None => return Err(PermError::Syntax("unclosed cycle".to_string())),
+                    Some(')') => break,
+                    Some(c) if c.is_whitespace() || c == ',' => continue,
+                    Some(c) if c.is_ascii_digit() => {
+                        let mut num = c.to_string();
+                        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                            num.push(*d);
+                            chars.next();
+                        }
+                        let point : usize = num.parse().ok()
+                            .and_then(|x: usize| x.checked_sub(first))
+                            .ok_or_else(|| PermError::Syntax(format!("invalid point {}", num)))?;
+                        if point >= degree {
+                            return Err(PermError::OutOfRange { image: point + first, degree });
+                        }
+                        if seen[point] {
+                            return Err(PermError::RepeatedImage { image: point + first });
+                        }
+                        seen[point] = true;
+                        cyc.push(point);
+                    }
+                    Some(c) => return Err(PermError::Syntax(format!("unexpected '{}'", c))),
+                }
+            }
+            cycles.push(cyc);
+        }
+        Ok(Perm::new_cyc(degree, cycles))
+    }
+
+    /// The permutation of the given degree mapping from[i] to to[i]. Every
+    /// other point is fixed where possible, and the points that cannot be
+    /// fixed because their images are taken are sent in increasing order to
+    /// the images left over
+    pub fn from_mapping(degree: usize, from: &[usize], to: &[usize]) -> Result<Perm, PermError> {
+        assert_eq!(from.len(), to.len());
+        let mut p : Vec<Option<usize>> = vec![None; degree];
+        let mut used = vec![false; degree];
+        for (&a, &b) in from.iter().zip(to.iter()) {
+            for &x in [a, b].iter() {
+                if x >= degree {
+                    return Err(PermError::OutOfRange { image: x, degree });
+                }
+            }
+            if p[a].is_some() {
+                return Err(PermError::RepeatedPoint { point: a });
+            }
+            if used[b] {
+                return Err(PermError::RepeatedImage { image: b });
+            }
+            p[a] = Some(b);
+            used[b] = true;
+        }
+        for x in 0..degree {
+            if p[x].is_none() && !used[x] {
+                p[x] = Some(x);
+                used[x] = true;
+            }
+        }
+        let mut free = (0..degree).filter(|&x| !used[x]);
+        Ok(Perm::new(p.into_iter().map(|x| x.or_else(|| free.next()).unwrap()).collect()))
+    }
+
+    /// the identity of the same degree as self
+    pub fn identity_like(&self) -> Perm {
+        Perm::id(self.p.len())
     }
 
     pub fn id(sz: usize) -> Perm {
-        return Perm::new((0..(sz)).collect())
+        assert!(sz <= u32::MAX as usize, "degree {} is too large", sz);
+        Perm { p: (0..(sz as u32)).collect() }
     }
 
     pub fn len(&self) -> usize {
-        return self.p.len()
+        self.p.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.p.is_empty()
+    }
+
+    /// whether every point is fixed, stopping at the first point that moves
+    pub fn is_identity(&self) -> bool {
+        self.p.iter().enumerate().all(|(i, &x)| x as usize == i)
     }
 
     pub fn inv(&self) -> Perm {
         let mut r = vec![0; self.p.len()];
-        for i in 0..(self.p.len()) {
-            r[self.p[i]] = i
+        for (i, &x) in self.p.iter().enumerate() {
+            r[x as usize] = i as u32
         }
-        return Perm::new(r)
+        Perm { p: r }
     }
 
     /// compute self * b (apply the permutation b, then self)
     pub fn compose(&self, b: &Perm) -> Perm {
         assert_eq!(self.p.len(), b.p.len());
+        let r = b.p.iter().map(|&x| self.p[x as usize]).collect();
+        Perm { p: r }
+    }
+
+    /// the commutator self * other * self^-1 * other^-1, which is the
+    /// identity iff the two commute. Since it is (self other)(other self)^-1
+    /// it maps other(self(j)) to self(other(j)), which is computed in one
+    /// pass without forming any inverses
+    pub fn commutator(&self, other: &Perm) -> Perm {
+        assert_eq!(self.p.len(), other.p.len());
         let mut r = vec![0; self.p.len()];
+        for j in 0..self.p.len() {
+            r[other.apply(self.apply(j))] = self.p[other.apply(j)];
+        }
+        Perm { p: r }
+    }
+
+    /// the permutation of degree self.len() + other.len() acting as self on
+    /// the first self.len() points and as other, shifted up, on the rest
+    pub fn direct_sum(&self, other: &Perm) -> Perm {
+        let shift = self.p.len() as u32;
+        let mut r = self.p.clone();
+        r.extend(other.p.iter().map(|&x| x + shift));
+        Perm { p: r }
+    }
+
+    /// the n x n permutation matrix with a 1 in row i, column self(i) and 0
+    /// elsewhere. With this convention a row vector times the matrix moves
+    /// entry i to position self(i), and the matrix of a.compose(b) is the
+    /// matrix of b times the matrix of a
+    pub fn to_matrix(&self) -> Vec<Vec<u8>> {
+        let n = self.p.len();
+        (0..n).map(|i| {
+            let mut row = vec![0; n];
+            row[self.apply(i)] = 1;
+            row
+        }).collect()
+    }
+
+    /// the permutation whose matrix, in the convention of `to_matrix`, is m;
+    /// each row must have length n and a single 1 among 0s, and each column
+    /// must have a single 1
+    pub fn from_matrix(m: &[Vec<u8>]) -> Result<Perm, PermError> {
+        let v = m.iter().enumerate().map(|(i, row)| {
+            let ones : Vec<usize> = (0..row.len()).filter(|&j| row[j] == 1).collect();
+            if row.len() != m.len() || ones.len() != 1 || row.iter().any(|&x| x > 1) {
+                return Err(PermError::BadMatrixRow { row: i });
+            }
+            Ok(ones[0])
+        }).collect::<Result<Vec<usize>, PermError>>()?;
+        Perm::try_new(v)
+    }
+
+    /// the same permutation with degree extended to new_degree, fixing the
+    /// added points; panics if new_degree is less than the current degree
+    pub fn embed(&self, new_degree: usize) -> Perm {
+        assert!(new_degree >= self.p.len(),
+                "cannot embed a permutation of degree {} into degree {}", self.p.len(), new_degree);
+        self.direct_sum(&Perm::id(new_degree - self.p.len()))
+    }
+
+    /// the permutation induced on a set that self preserves, where the
+    /// point subset[i] is renumbered to i
+    pub fn restrict(&self, subset: &[usize]) -> Result<Perm, PermError> {
+        let mut index = vec![None; self.p.len()];
+        for (i, &x) in subset.iter().enumerate() {
+            if x >= self.p.len() {
+                return Err(PermError::OutOfRange { image: x, degree: self.p.len() });
+            }
+            index[x] = Some(i);
+        }
+        let r = subset.iter()
+            .map(|&x| index[self.apply(x)].ok_or(PermError::NotPreserved { point: x, image: self.apply(x) }))
+            .collect::<Result<Vec<usize>, PermError>>()?;
+        Perm::try_new(r)
+    }
+
+    /// compute self * b like `compose`, but return an error rather than
+    /// panicking if the degrees differ
+    pub fn try_compose(&self, b: &Perm) -> Result<Perm, PermError> {
+        if self.p.len() != b.p.len() {
+            return Err(PermError::DegreeMismatch { left: self.p.len(), right: b.p.len() });
+        }
+        Ok(self.compose(b))
+    }
+
+    /// writes self o b into out without allocating, so callers can reuse a
+    /// scratch permutation in hot loops
+    pub fn compose_into(&self, b: &Perm, out: &mut Perm) {
+        assert_eq!(self.p.len(), b.p.len());
+        assert_eq!(self.p.len(), out.p.len());
+        for (o, &x) in out.p.iter_mut().zip(b.p.iter()) {
+            *o = self.p[x as usize];
+        }
+    }
+
+    /// The sorted list of points moved by the permutation
+    pub fn support(&self) -> Vec<usize> {
+        (0..(self.p.len())).filter(|&i| self.apply(i) != i).collect()
+    }
+
+    /// The sorted list of points fixed by the permutation
+    pub fn fixed_points(&self) -> Vec<usize> {
+        (0..(self.p.len())).filter(|&i| self.apply(i) == i).collect()
+    }
+
+    /// The sign of the permutation, (-1)^(n - number of cycles); a cycle of
+    /// length l contributes l - 1 transpositions
+    pub fn parity(&self) -> i8 {
+        let transpositions : usize = self.cycles().iter().map(|c| c.len() - 1).sum();
+        if transpositions.is_multiple_of(2) { 1 } else { -1 }
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.parity() == 1
+    }
+
+    /// The number of pairs i < j with self(i) > self(j)
+    // TODO: this is O(n^2); a merge-sort based O(n log n) count would be welcome
+    pub fn num_inversions(&self) -> usize {
+        let mut r = 0;
         for i in 0..(self.p.len()) {
-            r[i] = self.apply(b.apply(i))
+            r += self.p[(i+1)..].iter().filter(|&&x| x < self.p[i]).count();
+        }
+        r
+    }
+
+    /// Rearrange `items` by moving the item at position i to position
+    /// self(i); that is, position i of the result holds items[self^-1(i)]
+    pub fn permute_slice<T: Clone>(&self, items: &[T]) -> Vec<T> {
+        assert_eq!(items.len(), self.p.len());
+        let mut r = items.to_vec();
+        for (i, x) in items.iter().enumerate() {
+            r[self.apply(i)] = x.clone();
+        }
+        r
+    }
+
+    /// compute by * self * by^-1 without explicitly building the inverse:
+    /// the conjugate sends by(i) to by(self(i))
+    pub fn conjugate(&self, by: &Perm) -> Perm {
+        assert_eq!(self.p.len(), by.p.len());
+        let mut r = vec![0; self.p.len()];
+        for i in 0..(self.p.len()) {
+            r[by.apply(i)] = by.p[self.apply(i)]
+        }
+        Perm { p: r }
+    }
+
+    /// Compute self^k by repeated squaring; negative powers are powers of
+    /// the inverse
+    pub fn pow(&self, k: i64) -> Perm {
+        let mut base = if k < 0 { self.inv() } else { self.clone() };
+        let mut e = k.unsigned_abs();
+        let mut r = Perm::id(self.p.len());
+        while e > 0 {
+            if e & 1 == 1 {
+                r = r.compose(&base);
+            }
+            base = base.compose(&base);
+            e >>= 1;
+        }
+        r
+    }
+
+    /// Compute the order of the permutation, i.e. the smallest k > 0 such
+    /// that self^k is the identity; this is the lcm of the cycle lengths.
+    /// Panics if the order does not fit in a usize, which can happen from
+    /// degree a few hundred on; use `order_big` for those
+    pub fn order(&self) -> usize {
+        self.cycles().iter().try_fold(1, |r, c| checked_lcm(r, c.len()))
+            .expect("permutation order overflows usize, use order_big")
+    }
+
+    /// Compute the order of the permutation without overflow, as the
+    /// product of the highest power of each prime dividing a cycle length
+    pub fn order_big(&self) -> BigUint {
+        let mut powers : BTreeMap<usize, usize> = BTreeMap::new();
+        for c in self.cycles() {
+            let mut m = c.len();
+            let mut q = 2;
+            while m > 1 {
+                if q * q > m { q = m }
+                let mut power = 1;
+                while m % q == 0 {
+                    m /= q;
+                    power *= q;
+                }
+                if power > 1 {
+                    let best = powers.entry(q).or_insert(1);
+                    *best = (*best).max(power);
+                }
+                q += 1;
+            }
+        }
+        powers.values().map(|&x| BigUint::from(x)).product()
+    }
+
+    /// Decompose the permutation into disjoint cycles, omitting fixed points.
+    /// Each cycle starts at its least element and cycles are ordered by their
+    /// least element, so `Perm::new_cyc(self.len(), self.cycles()) == self`
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.p.len()];
+        let mut r = Vec::new();
+        for start in 0..(self.p.len()) {
+            if visited[start] { continue }
+            // walk the cycle containing `start`; since we scan points in
+            // increasing order, `start` is the least point in its cycle
+            let mut c = Vec::new();
+            let mut cur = start;
+            while !visited[cur] {
+                visited[cur] = true;
+                c.push(cur);
+                cur = self.apply(cur);
+            }
+            if c.len() > 1 {
+                r.push(c);
+            }
+        }
+        r
+    }
+
+    /// The cycle type of self^k without computing the power: a cycle of
+    /// length l splits into gcd(l, k) cycles of length l / gcd(l, k)
+    pub fn power_cycle_type(&self, k: usize) -> Vec<usize> {
+        let mut r = Vec::new();
+        for l in self.cycle_type() {
+            let d = gcd(l, k);
+            r.resize(r.len() + d, l / d);
+        }
+        r.sort_unstable_by(|a, b| b.cmp(a));
+        r
+    }
+
+    /// Whether the permutation has exactly one nontrivial cycle
+    pub fn is_single_cycle(&self) -> bool {
+        self.cycles().len() == 1
+    }
+
+    /// The cycle containing `point`, starting from it: point, self(point),
+    /// self(self(point)), ...; a fixed point gives a cycle of length 1
+    pub fn cycle_of_point(&self, point: usize) -> Vec<usize> {
+        let mut c = vec![point];
+        let mut cur = self.apply(point);
+        while cur != point {
+            c.push(cur);
+            cur = self.apply(cur);
         }
-        return Perm::new(r);
+        c
+    }
+
+    /// The lengths of all the cycles, including fixed points as cycles of
+    /// length 1, in non-increasing order; this partition of the degree
+    /// determines the conjugacy class in the symmetric group
+    pub fn cycle_type(&self) -> Vec<usize> {
+        let mut r : Vec<usize> = self.cycles().iter().map(|c| c.len()).collect();
+        r.sort_unstable_by(|a, b| b.cmp(a));
+        let moved : usize = r.iter().sum();
+        r.resize(r.len() + self.p.len() - moved, 1);
+        r
+    }
+}
+
+pub(crate) fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+pub(crate) fn checked_lcm(a: usize, b: usize) -> Option<usize> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+pub(crate) fn lcm(a: usize, b: usize) -> usize {
+    checked_lcm(a, b).expect("lcm overflows usize")
+}
+
+impl From<Vec<usize>> for Perm {
+    fn from(v: Vec<usize>) -> Perm {
+        Perm::new(v)
+    }
+}
+
+impl FromIterator<usize> for Perm {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Perm {
+        Perm::new(iter.into_iter().collect())
+    }
+}
+
+// the serialized form of a permutation, its images; unlike `From`, reading
+// one back always validates it
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+struct PermImages(Vec<usize>);
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<PermImages> for Perm {
+    type Error = PermError;
+    fn try_from(v: PermImages) -> Result<Perm, PermError> {
+        Perm::try_new(v.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Perm> for PermImages {
+    fn from(p: Perm) -> PermImages {
+        PermImages(p.into())
+    }
+}
+
+impl From<Perm> for Vec<usize> {
+    fn from(p: Perm) -> Vec<usize> {
+        p.p.into_iter().map(|x| x as usize).collect()
     }
 }
 
@@ -58,6 +548,61 @@ impl PartialEq for Perm {
     }
 }
 
+/// `&a * &b` is `a.compose(&b)`: apply b first, then a
+impl Mul<&Perm> for &Perm {
+    type Output = Perm;
+
+    fn mul(self, b: &Perm) -> Perm {
+        self.compose(b)
+    }
+}
+
+/// `a * b` is `a.compose(&b)`: apply b first, then a
+impl Mul for Perm {
+    type Output = Perm;
+
+    fn mul(self, b: Perm) -> Perm {
+        self.compose(&b)
+    }
+}
+
+/// Permutations of the same degree are ordered lexicographically by their
+/// images
+impl Ord for Perm {
+    fn cmp(&self, other: &Self) -> Ordering {
+        debug_assert_eq!(self.p.len(), other.p.len());
+        self.p.cmp(&other.p)
+    }
+}
+
+impl PartialOrd for Perm {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Perm {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.p.hash(state);
+    }
+}
+
+/// Prints the permutation in cycle notation, e.g. `(1 3 8 6)(2 5 7 4)`; the
+/// identity is printed as `()`
+impl fmt::Display for Perm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cycles = self.cycles();
+        if cycles.is_empty() {
+            return write!(f, "()");
+        }
+        for c in cycles.iter() {
+            let pts : Vec<String> = c.iter().map(|x| x.to_string()).collect();
+            write!(f, "({})", pts.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
 
 
 #[test]
@@ -73,3 +618,332 @@ fn inv_test() {
     assert_eq!(p1.compose(&p1.inv()), Perm::id(9))
 }
 
+#[test]
+fn order_test() {
+    assert_eq!(Perm::id(5).order(), 1);
+    for g in super::group::Group::rubiks().get_gens() {
+        assert_eq!(g.order(), 4);
+    }
+    let p = Perm::new_cyc(6, vec![vec![0, 1, 2], vec![3, 4]]);
+    assert_eq!(p.order(), 6);
+    assert_eq!(p.order_big(), BigUint::from(6_u32));
+    assert_eq!(Perm::id(5).order_big(), BigUint::from(1_u32));
+}
+
+// disjoint cycles of every prime length up to 71, on 639 points
+#[cfg(test)]
+fn prime_cycles_perm() -> Perm {
+    let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+    let mut start = 0;
+    let mut cycles = Vec::new();
+    for &l in primes.iter() {
+        cycles.push((start..(start + l)).collect());
+        start += l;
+    }
+    Perm::new_cyc(start, cycles)
+}
+
+#[test]
+fn order_big_test() {
+    let p = prime_cycles_perm();
+    assert_eq!(p.len(), 639);
+    let expected = [2_u32, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71]
+        .iter().map(|&x| BigUint::from(x)).product::<BigUint>();
+    assert_eq!(p.order_big(), expected);
+    // repeated prime powers only count once
+    let q = Perm::new_cyc(12, vec![vec![0, 1, 2, 3], vec![4, 5], vec![6, 7, 8, 9, 10, 11]]);
+    assert_eq!(q.order_big(), BigUint::from(12_u32));
+    assert_eq!(q.order_big(), BigUint::from(q.order()));
+}
+
+#[test]
+#[should_panic(expected = "overflows usize")]
+fn order_overflow_test() {
+    prime_cycles_perm().order();
+}
+
+#[test]
+fn cycles_test() {
+    let input = vec![vec![4, 2], vec![3, 1, 0]];
+    let p = Perm::new_cyc(5, input);
+    assert_eq!(p.cycles(), vec![vec![0, 3, 1], vec![2, 4]]);
+    assert_eq!(Perm::new_cyc(5, p.cycles()), p);
+    assert!(Perm::id(5).cycles().is_empty());
+}
+
+#[test]
+fn display_test() {
+    assert_eq!(format!("{}", Perm::id(4)), "()");
+    let p = Perm::new_cyc(9, vec![vec![6, 1, 3, 8], vec![2, 5, 7, 4]]);
+    assert_eq!(format!("{}", p), "(1 3 8 6)(2 5 7 4)");
+}
+
+#[test]
+fn hash_test() {
+    use std::collections::HashSet;
+    let mut seen: HashSet<Perm> = HashSet::new();
+    seen.insert(Perm::new(vec![1, 2, 0, 3]));
+    seen.insert(Perm::new_cyc(4, vec![vec![0, 1, 2]]));
+    assert_eq!(seen.len(), 1);
+}
+
+#[test]
+fn pow_test() {
+    let p = Perm::new_cyc(7, vec![vec![0, 1, 2], vec![3, 4], vec![5, 6]]);
+    assert_eq!(p.pow(p.order() as i64), Perm::id(p.len()));
+    assert_eq!(p.pow(0), Perm::id(p.len()));
+    assert_eq!(p.pow(-1), p.inv());
+    assert_eq!(p.pow(5), p.compose(&p).compose(&p).compose(&p).compose(&p));
+    assert_eq!(p.pow(-2), p.inv().compose(&p.inv()));
+}
+
+#[test]
+fn conjugate_test() {
+    let p = Perm::new_cyc(4, vec![vec![0, 1, 2]]);
+    let by = Perm::new_cyc(4, vec![vec![0, 3]]);
+    assert_eq!(p.conjugate(&by), Perm::new_cyc(4, vec![vec![3, 1, 2]]));
+    assert_eq!(p.conjugate(&by), by.compose(&p).compose(&by.inv()));
+}
+
+#[test]
+fn support_test() {
+    let g = super::group::Group::rubiks().get_gens()[0].clone();
+    assert_eq!(g.support().len(), 20);
+    assert_eq!(g.fixed_points().len(), 29);
+    assert!(g.fixed_points().contains(&0));
+    assert!(!g.support().contains(&0));
+}
+
+#[test]
+fn try_new_test() {
+    assert_eq!(Perm::try_new(vec![0, 0, 1]), Err(PermError::RepeatedImage { image: 0 }));
+    assert_eq!(Perm::try_new(vec![0, 3, 1]), Err(PermError::OutOfRange { image: 3, degree: 3 }));
+    assert_eq!(Perm::try_new(vec![2, 0, 1]), Ok(Perm::new(vec![2, 0, 1])));
+}
+
+#[test]
+fn parse_test() {
+    let p = Perm::parse(9, "(1 3 8 6)(2, 5, 7, 4)").unwrap();
+    assert_eq!(p, Perm::new_cyc(9, vec![vec![1, 3, 8, 6], vec![2, 5, 7, 4]]));
+    assert_eq!(Perm::parse(3, " () ").unwrap(), Perm::id(3));
+    assert_eq!(Perm::parse(3, "(0 3)"), Err(PermError::OutOfRange { image: 3, degree: 3 }));
+    assert_eq!(Perm::parse(3, "(0 1)(1 2)"), Err(PermError::RepeatedImage { image: 1 }));
+    assert!(Perm::parse(3, "(0 1").is_err());
+    assert!(Perm::parse(3, "0 1").is_err());
+}
+
+#[test]
+fn mul_test() {
+    let a = Perm::new_cyc(4, vec![vec![0, 1, 2]]);
+    let b = Perm::new_cyc(4, vec![vec![2, 3]]);
+    assert_eq!(&a * &b, a.compose(&b));
+    assert_eq!(&b * &a, b.compose(&a));
+    assert_ne!(&a * &b, &b * &a);
+    assert_eq!(a.clone() * b.clone(), a.compose(&b));
+}
+
+#[test]
+fn ord_test() {
+    assert!(Perm::id(3) < Perm::new(vec![0, 2, 1]));
+    let mut gens = vec![Perm::new(vec![2, 1, 0]), Perm::id(3), Perm::new(vec![1, 0, 2])];
+    gens.sort();
+    assert_eq!(gens, vec![Perm::id(3), Perm::new(vec![1, 0, 2]), Perm::new(vec![2, 1, 0])]);
+}
+
+#[test]
+fn permute_slice_test() {
+    let p = Perm::new_cyc(3, vec![vec![0, 1, 2]]);
+    assert_eq!(p.permute_slice(&['a', 'b', 'c']), vec!['c', 'a', 'b']);
+    // permuting by a composition is permuting by each in turn
+    let q = Perm::new_cyc(3, vec![vec![0, 1]]);
+    assert_eq!(p.compose(&q).permute_slice(&['a', 'b', 'c']),
+               p.permute_slice(&q.permute_slice(&['a', 'b', 'c'])));
+}
+
+#[test]
+fn parity_test() {
+    assert!(Perm::id(4).is_even());
+    assert_eq!(Perm::new_cyc(4, vec![vec![1, 3]]).parity(), -1);
+    assert!(Perm::new_cyc(4, vec![vec![0, 1, 2]]).is_even());
+    assert!(Perm::new_cyc(4, vec![vec![0, 1], vec![2, 3]]).is_even());
+    assert!(!Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]).is_even());
+}
+
+#[test]
+fn num_inversions_test() {
+    assert_eq!(Perm::id(6).num_inversions(), 0);
+    let n = 7;
+    let rev = Perm::new((0..n).rev().collect());
+    assert_eq!(rev.num_inversions(), n*(n-1)/2);
+    assert_eq!(Perm::new(vec![1, 2, 0]).num_inversions(), 2);
+}
+
+#[test]
+fn size_test() {
+    use std::mem::{size_of, size_of_val};
+    // the images are stored as u32 rather than usize
+    assert_eq!(size_of::<Perm>(), size_of::<Vec<u32>>());
+    let p = Perm::id(49);
+    assert_eq!(size_of_val(&p.p[0]), 4);
+    assert_eq!(size_of_val(p.p.as_slice()), 196);
+    assert_eq!(p.apply(48), 48);
+}
+
+#[test]
+fn compose_into_test() {
+    let a = Perm::new_cyc(5, vec![vec![0, 1, 2]]);
+    let b = Perm::new_cyc(5, vec![vec![1, 4], vec![2, 3]]);
+    let mut out = Perm::id(5);
+    a.compose_into(&b, &mut out);
+    assert_eq!(out, a.compose(&b));
+    b.compose_into(&a, &mut out);
+    assert_eq!(out, b.compose(&a));
+}
+
+#[test]
+fn direct_sum_test() {
+    let t = Perm::new(vec![1, 0]);
+    assert_eq!(t.direct_sum(&t), Perm::new(vec![1, 0, 3, 2]));
+    let c = Perm::new_cyc(3, vec![vec![0, 1, 2]]);
+    assert_eq!(Perm::id(2).direct_sum(&c), Perm::new_cyc(5, vec![vec![2, 3, 4]]));
+    assert_eq!(c.direct_sum(&Perm::id(0)), c);
+}
+
+#[test]
+fn restrict_test() {
+    let g = super::group::Group::rubiks().get_gens()[0].clone();
+    let r = g.restrict(&[1, 3, 8, 6]).unwrap();
+    assert_eq!(r, Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]));
+    let r = g.restrict(&[0, 6, 8, 3, 1]).unwrap();
+    assert_eq!(r, Perm::new_cyc(5, vec![vec![1, 4, 3, 2]]));
+    assert_eq!(g.restrict(&[1, 3]), Err(PermError::NotPreserved { point: 3, image: 8 }));
+    assert_eq!(g.restrict(&[49]), Err(PermError::OutOfRange { image: 49, degree: 49 }));
+}
+
+#[test]
+fn embed_test() {
+    let t = Perm::new(vec![1, 0]);
+    let e = t.embed(5);
+    assert_eq!(e, Perm::new_cyc(5, vec![vec![0, 1]]));
+    assert_eq!(e.fixed_points(), vec![2, 3, 4]);
+    assert_eq!(t.embed(2), t);
+}
+
+#[test]
+#[should_panic]
+fn embed_smaller_test() {
+    Perm::id(3).embed(2);
+}
+
+#[test]
+fn from_mapping_test() {
+    let p = Perm::from_mapping(4, &[0, 2], &[2, 0]).unwrap();
+    assert_eq!(p, Perm::new_cyc(4, vec![vec![0, 2]]));
+    // 1 cannot stay fixed once 0 maps to it, so it takes the free image 0
+    let p = Perm::from_mapping(4, &[0], &[1]).unwrap();
+    assert_eq!(p, Perm::new(vec![1, 0, 2, 3]));
+    assert_eq!(Perm::from_mapping(3, &[0, 1], &[2, 2]), Err(PermError::RepeatedImage { image: 2 }));
+    assert_eq!(Perm::from_mapping(3, &[0, 0], &[1, 2]), Err(PermError::RepeatedPoint { point: 0 }));
+    assert_eq!(Perm::from_mapping(3, &[3], &[0]), Err(PermError::OutOfRange { image: 3, degree: 3 }));
+}
+
+#[test]
+fn is_identity_test() {
+    assert!(Perm::id(5).is_identity());
+    assert!(Perm::id(0).is_identity());
+    assert!(!Perm::new_cyc(5, vec![vec![3, 4]]).is_identity());
+    let p = Perm::new_cyc(5, vec![vec![0, 1, 2]]);
+    assert!(p.compose(&p.inv()).is_identity());
+}
+
+#[test]
+fn cycle_type_test() {
+    let p = Perm::new_cyc(5, vec![vec![3, 4], vec![0, 1, 2]]);
+    assert_eq!(p.cycle_type(), vec![3, 2]);
+    assert_eq!(Perm::new_cyc(5, vec![vec![1, 3]]).cycle_type(), vec![2, 1, 1, 1]);
+    assert_eq!(Perm::id(3).cycle_type(), vec![1, 1, 1]);
+    assert_eq!(Perm::id(0).cycle_type(), Vec::<usize>::new());
+}
+
+#[test]
+fn commutator_test() {
+    let a = Perm::new_cyc(5, vec![vec![0, 1]]);
+    let b = Perm::new_cyc(5, vec![vec![2, 3, 4]]);
+    assert!(a.commutator(&b).is_identity());
+    let a = Perm::new_cyc(3, vec![vec![0, 1, 2]]);
+    let b = Perm::new_cyc(3, vec![vec![0, 1]]);
+    let c = a.commutator(&b);
+    assert_eq!(c, a.compose(&b).compose(&a.inv()).compose(&b.inv()));
+    assert_eq!(c.cycle_type(), vec![3]);
+}
+
+#[test]
+fn from_iter_test() {
+    let p : Perm = (0..5).rev().collect();
+    assert_eq!(p, Perm::new(vec![4, 3, 2, 1, 0]));
+    assert_eq!(Perm::from(vec![1, 2, 0]), Perm::new(vec![1, 2, 0]));
+    let q : Perm = (0..4).map(|i| (i + 1) % 4).collect();
+    assert_eq!(q, Perm::new_cyc(4, vec![vec![0, 1, 2, 3]]));
+}
+
+#[test]
+fn try_compose_test() {
+    let a = Perm::new_cyc(3, vec![vec![0, 1, 2]]);
+    assert_eq!(a.try_compose(&a), Ok(a.compose(&a)));
+    assert_eq!(a.try_compose(&Perm::id(4)), Err(PermError::DegreeMismatch { left: 3, right: 4 }));
+    assert_eq!(a.identity_like(), Perm::id(3));
+}
+
+#[test]
+fn single_cycle_test() {
+    let p = Perm::new_cyc(5, vec![vec![0, 3, 1, 4, 2]]);
+    assert!(p.is_single_cycle());
+    let mut c = p.cycle_of_point(1);
+    assert_eq!(c, vec![1, 4, 2, 0, 3]);
+    c.sort_unstable();
+    assert_eq!(c, vec![0, 1, 2, 3, 4]);
+    let q = Perm::new_cyc(4, vec![vec![0, 1], vec![2, 3]]);
+    assert!(!q.is_single_cycle());
+    assert_eq!(q.cycle_of_point(3), vec![3, 2]);
+    assert!(Perm::new_cyc(4, vec![vec![1, 2]]).is_single_cycle());
+    assert!(!Perm::id(4).is_single_cycle());
+    assert_eq!(Perm::id(4).cycle_of_point(2), vec![2]);
+}
+
+#[test]
+fn power_cycle_type_test() {
+    let p = Perm::new_cyc(6, vec![vec![0, 1, 2, 3, 4, 5]]);
+    assert_eq!(p.power_cycle_type(2), vec![3, 3]);
+    assert_eq!(p.power_cycle_type(2), p.pow(2).cycle_type());
+    let q = Perm::new_cyc(7, vec![vec![0, 1, 2, 3], vec![4, 5, 6]]);
+    for k in 0..13 {
+        assert_eq!(q.power_cycle_type(k), q.pow(k as i64).cycle_type());
+    }
+}
+
+#[test]
+fn to_matrix_test() {
+    fn matmul(a: &[Vec<u8>], b: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        (0..a.len()).map(|i| (0..a.len()).map(|k| (0..a.len()).map(|j| a[i][j] * b[j][k]).sum()).collect()).collect()
+    }
+    let c = Perm::new_cyc(3, vec![vec![0, 1, 2]]);
+    let m = c.to_matrix();
+    assert_eq!(m, vec![vec![0, 1, 0], vec![0, 0, 1], vec![1, 0, 0]]);
+    assert_eq!(m.iter().flatten().map(|&x| x as usize).sum::<usize>(), 3);
+    let a = Perm::new_cyc(4, vec![vec![0, 1, 2]]);
+    let b = Perm::new_cyc(4, vec![vec![1, 3]]);
+    assert_eq!(a.compose(&b).to_matrix(), matmul(&b.to_matrix(), &a.to_matrix()));
+    assert_eq!(b.compose(&a).to_matrix(), matmul(&a.to_matrix(), &b.to_matrix()));
+    assert_eq!(Perm::id(3).to_matrix(), matmul(&m, &c.inv().to_matrix()));
+}
+
+#[test]
+fn from_matrix_test() {
+    let p = Perm::new_cyc(5, vec![vec![0, 3], vec![1, 4, 2]]);
+    assert_eq!(Perm::from_matrix(&p.to_matrix()), Ok(p));
+    assert_eq!(Perm::from_matrix(&[]), Ok(Perm::id(0)));
+    assert_eq!(Perm::from_matrix(&[vec![1, 0], vec![1, 0]]), Err(PermError::RepeatedImage { image: 0 }));
+    assert_eq!(Perm::from_matrix(&[vec![1, 1], vec![0, 1]]), Err(PermError::BadMatrixRow { row: 0 }));
+    assert_eq!(Perm::from_matrix(&[vec![1, 0], vec![0, 2]]), Err(PermError::BadMatrixRow { row: 1 }));
+    assert_eq!(Perm::from_matrix(&[vec![1, 0], vec![0]]), Err(PermError::BadMatrixRow { row: 1 }));
+}
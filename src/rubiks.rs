@@ -0,0 +1,191 @@
+use super::group::*;
+use super::perm::*;
+use rand::{Rng, SeedableRng};
+
+use std::collections::HashMap;
+
+// words longer than this are dropped while filling the table
+const WORD_LIMIT: usize = 1000;
+
+// one level of the solver's table: for each point q in the basic orbit, an
+// element fixing the earlier base points that maps the base point to q,
+// together with a short word for it
+struct Level {
+    point: usize,
+    size: usize,
+    reps: HashMap<usize, (Perm, Word)>,
+}
+
+/// Solves scrambles of `Group::rubiks()` by sifting them through a table of
+/// coset representatives that carry short words in the face turns, built
+/// with Minkwitz's method. Building the table takes some time, so reuse one
+/// solver for many scrambles
+pub struct RubiksSolver {
+    gens: Vec<Perm>,
+    levels: Vec<Level>,
+}
+
+impl RubiksSolver {
+    pub fn new() -> RubiksSolver {
+        let mut g = Group::rubiks();
+        let gens = g.get_gens().clone();
+        // stabilize the corner facets first, then the edge facets, since the
+        // corners are moved by fewer of the short words
+        let (corners, edges) : (Vec<usize>, Vec<usize>) = (1..49).partition(|p| [0, 2, 5, 7].contains(&((p - 1) % 8)));
+        let base : Vec<usize> = corners.into_iter().chain(edges).collect();
+        g.schreier_sims_with_base(&base);
+        let chain = g.stabilizer_chain();
+        let levels = (0..chain.len())
+            .map(|l| (chain.base()[l], chain.orbit(l).len()))
+            .filter(|&(_, size)| size > 1)
+            .map(|(point, size)| {
+                let mut reps = HashMap::new();
+                reps.insert(point, (Perm::id(49), Vec::new()));
+                Level { point, size, reps }
+            })
+            .collect();
+        let mut solver = RubiksSolver { gens, levels };
+        solver.fill();
+        solver
+    }
+
+    // sift random words into the table until every level is complete,
+    // periodically combining the entries already found to shorten them
+    fn fill(&mut self) {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut rounds = 0;
+        while self.levels.iter().any(|l| l.reps.len() < l.size) {
+            let mut t = Perm::id(49);
+            let mut w : Word = Vec::new();
+            for _ in 0..rng.gen_range(1..20) {
+                let letter = (rng.gen_range(0..self.gens.len()), rng.gen_bool(0.5));
+                let x = if letter.1 { self.gens[letter.0].inv() } else { self.gens[letter.0].clone() };
+                t = t.compose(&x);
+                w = word_mul(&w, &vec![letter]);
+            }
+            self.insert(0, t, w);
+            rounds += 1;
+            if rounds % 500 == 0 {
+                self.improve();
+            }
+        }
+        // the last entries found have long words, so combine a few more times
+        for _ in 0..3 {
+            self.improve();
+        }
+    }
+
+    // multiply every pair of entries at each level and sift the products,
+    // which fix the same base points
+    fn improve(&mut self) {
+        for l in 0..self.levels.len() {
+            let entries : Vec<(Perm, Word)> = self.levels[l].reps.values().cloned().collect();
+            for (a, aw) in entries.iter() {
+                for (b, bw) in entries.iter() {
+                    self.insert(l, a.compose(b), word_mul(aw, bw));
+                }
+            }
+        }
+    }
+
+    // sift t, which fixes the base points above `start`, into the table;
+    // new points are recorded, and a shorter word for a known point replaces
+    // the old entry, which is then sifted further in its place
+    fn insert(&mut self, start: usize, mut t: Perm, mut w: Word) {
+        for level in self.levels[start..].iter_mut() {
+            let q = t.apply(level.point);
+            match level.reps.get_mut(&q) {
+                None => {
+                    let ti = t.inv();
+                    let qi = ti.apply(level.point);
+                    level.reps.insert(q, (t, w.clone()));
+                    level.reps.entry(qi).or_insert_with(|| (ti, word_inv(&w)));
+                    return;
+                }
+                Some(entry) => {
+                    if w.len() < entry.1.len() {
+                        std::mem::swap(&mut entry.0, &mut t);
+                        std::mem::swap(&mut entry.1, &mut w);
+                    }
+                    t = entry.0.inv().compose(&t);
+                    w = word_mul(&word_inv(&entry.1), &w);
+                }
+            }
+            if w.len() > WORD_LIMIT || t.is_identity() {
+                return;
+            }
+        }
+    }
+
+    /// A sequence of face turns, as indices into the generators of
+    /// `Group::rubiks()`, whose product is the scramble; None if the scramble
+    /// is not a position of the cube. Inverse turns are written as three
+    /// quarter turns
+    pub fn solve(&self, scramble: &Perm) -> Option<Vec<usize>> {
+        if scramble.len() != 49 { return None }
+        let mut g = scramble.clone();
+        let mut word : Word = Vec::new();
+        for level in self.levels.iter() {
+            let (u, uw) = level.reps.get(&g.apply(level.point))?;
+            g = u.inv().compose(&g);
+            word = word_mul(&word, uw);
+        }
+        if !g.is_identity() { return None }
+        let mut moves = Vec::new();
+        for (m, inv) in word {
+            moves.extend(std::iter::repeat_n(m, if inv { 3 } else { 1 }));
+        }
+        Some(moves)
+    }
+}
+
+impl Default for RubiksSolver {
+    fn default() -> RubiksSolver {
+        RubiksSolver::new()
+    }
+}
+
+/// Solve a scramble of `Group::rubiks()` with a fresh `RubiksSolver`
+pub fn solve_rubiks(scramble: &Perm) -> Option<Vec<usize>> {
+    RubiksSolver::new().solve(scramble)
+}
+
+/// A random scramble of `length` quarter turns, returned both as the
+/// resulting position and as the turns, indices into the generators of
+/// `Group::rubiks()` whose product is the position. Inverse turns are not
+/// drawn separately since they are three quarter turns
+pub fn random_rubiks_scramble(rng: &mut impl Rng, length: usize) -> (Perm, Vec<usize>) {
+    let g = Group::rubiks();
+    let moves : Vec<usize> = (0..length).map(|_| rng.gen_range(0..g.num_gens())).collect();
+    let p = moves.iter().fold(Perm::id(49), |acc, &m| acc.compose(&g.get_gens()[m]));
+    (p, moves)
+}
+
+#[test]
+fn solve_rubiks_test() {
+    let g = Group::rubiks();
+    let solver = RubiksSolver::new();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+    for _ in 0..5 {
+        let scramble = g.random_element_seeded(&mut rng);
+        let word = solver.solve(&scramble).unwrap();
+        let p = word.iter().fold(Perm::id(49), |acc, &m| acc.compose(&g.get_gens()[m]));
+        assert_eq!(p, scramble);
+    }
+    assert_eq!(solver.solve(&Perm::id(49)), Some(vec![]));
+    // a single facet swap is not a position of the cube
+    assert_eq!(solver.solve(&Perm::new_cyc(49, vec![vec![1, 3]])), None);
+}
+
+#[test]
+fn random_rubiks_scramble_test() {
+    let mut g = Group::rubiks();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+    let (p, moves) = random_rubiks_scramble(&mut rng, 25);
+    assert_eq!(moves.len(), 25);
+    assert!(moves.iter().all(|&m| m < 6));
+    let q = moves.iter().fold(Perm::id(49), |acc, &m| acc.compose(&g.get_gens()[m]));
+    assert_eq!(p, q);
+    assert!(g.contains(&p));
+    assert_eq!(random_rubiks_scramble(&mut rng, 0).0, Perm::id(49));
+}